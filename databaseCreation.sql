@@ -3,4 +3,71 @@
 CREATE TABLE IF NOT EXISTS Players (
     uuid TEXT NOT NULL PRIMARY KEY,
     name TEXT NOT NULL UNIQUE
-) WITHOUT ROWID;
\ No newline at end of file
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS BotAccounts (
+    player_uuid TEXT NOT NULL PRIMARY KEY,
+    api_key_hash TEXT NOT NULL UNIQUE, -- sha256 of the api key, hex encoded
+    FOREIGN KEY (player_uuid) REFERENCES Players(uuid)
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS Replays (
+    game_id TEXT NOT NULL PRIMARY KEY,
+    lobby_id INTEGER NOT NULL,
+    data BLOB NOT NULL -- json encoded replay
+) WITHOUT ROWID;
+CREATE TABLE IF NOT EXISTS Maps (
+    map_id TEXT NOT NULL PRIMARY KEY,
+    name TEXT NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    nb_spawns INTEGER NOT NULL,
+    created_at INTEGER NOT NULL, -- unix timestamp seconds
+    data BLOB NOT NULL -- json encoded map, as uploaded
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS Games (
+    game_id TEXT NOT NULL PRIMARY KEY,
+    lobby_id INTEGER NOT NULL,
+    player_capacity INTEGER NOT NULL,
+    mode TEXT NOT NULL, -- free_for_all, teams or bots
+    started_at INTEGER NOT NULL, -- unix timestamp seconds
+    ended_at INTEGER NOT NULL, -- unix timestamp seconds
+    nb_ticks INTEGER NOT NULL,
+    winner_name TEXT, -- null unless a single player is left
+    winning_team INTEGER -- null when nobody won or outside of team games
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS GamePlayers (
+    game_id TEXT NOT NULL,
+    player_uuid TEXT NOT NULL,
+    player_name TEXT NOT NULL, -- name at the time of the game
+    placement INTEGER NOT NULL,
+    total_positions INTEGER NOT NULL,
+    total_troops INTEGER NOT NULL,
+    team INTEGER, -- null in free for all
+    PRIMARY KEY (game_id, player_uuid),
+    FOREIGN KEY (game_id) REFERENCES Games(game_id),
+    FOREIGN KEY (player_uuid) REFERENCES Players(uuid)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS GamePlayersByPlayer ON GamePlayers (player_uuid);
+CREATE INDEX IF NOT EXISTS GamesByEnd ON Games (ended_at);
+
+CREATE TABLE IF NOT EXISTS Ratings (
+    player_uuid TEXT NOT NULL PRIMARY KEY,
+    rating INTEGER NOT NULL,
+    nb_rated_games INTEGER NOT NULL,
+    FOREIGN KEY (player_uuid) REFERENCES Players(uuid)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS RatingHistory (
+    game_id TEXT NOT NULL,
+    player_uuid TEXT NOT NULL,
+    rating_before INTEGER NOT NULL,
+    rating_after INTEGER NOT NULL,
+    PRIMARY KEY (game_id, player_uuid),
+    FOREIGN KEY (game_id) REFERENCES Games(game_id),
+    FOREIGN KEY (player_uuid) REFERENCES Players(uuid)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS GamesByCapacity ON Games (player_capacity, ended_at);
+CREATE INDEX IF NOT EXISTS GamesByMode ON Games (mode, ended_at);
@@ -0,0 +1,272 @@
+// Headless games between built-in strategies, for balancing : no server and no database,
+// the same boards, engine and bots as the lobbies.
+//   cargo run --release --bin simulate -- --games 1000 --strategies hard,medium --format csv
+// Per game results go to stdout, the summary to stderr (or along with the games in json).
+use backend::service_layer::{
+    bot_service::{queue_bot_moves, BotDifficulty},
+    game_engine::{GameEngine, GameStatus, TickInputs},
+    game_service::place_players,
+    map_generator::{generate_map, MapKind},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const USAGE: &str = "usage: simulate [--games N] [--seed S] [--strategies easy,medium,hard,idle] \
+[--players N] [--team-size N] [--max-ticks N] [--map uniform|ranges|symmetric] [--format csv|json]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Csv,
+    Json,
+}
+
+#[derive(Debug)]
+struct Options {
+    nb_games: usize,
+    seed: u64, // game i is played on seed + i
+    strategies: Vec<String>,
+    nb_players: usize,
+    team_size: Option<usize>,
+    max_ticks: usize,
+    map_kind: MapKind,
+    format: Format,
+}
+
+#[derive(Debug, Serialize)]
+struct GameResult {
+    seed: u64,
+    nb_ticks: usize,
+    outcome: &'static str,
+    seats: Vec<String>,   // strategy of each player
+    winners: Vec<String>, // strategies of the winning player or team
+}
+
+#[derive(Debug, Default, Serialize)]
+struct StrategySummary {
+    nb_seats: usize, // a strategy can fill several seats of the same game
+    nb_wins: usize,
+    win_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Summary {
+    nb_games: usize,
+    nb_unfinished: usize, // stopped at max ticks
+    average_ticks: f64,
+    strategies: BTreeMap<String, StrategySummary>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    summary: Summary,
+    games: Vec<GameResult>,
+}
+
+fn main() {
+    let options = match parse_options(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    if options.format == Format::Csv {
+        println!("seed,nb_ticks,outcome,seats,winners");
+    }
+    let mut games = vec![];
+    for i in 0..options.nb_games {
+        // the seats rotate between games, the players are resolved in seat order
+        let seats: Vec<String> = (0..options.nb_players)
+            .map(|seat| options.strategies[(seat + i) % options.strategies.len()].clone())
+            .collect();
+        let result = simulate(options.seed.wrapping_add(i as u64), seats, &options);
+        if options.format == Format::Csv {
+            println!(
+                "{},{},{},{},{}",
+                result.seed,
+                result.nb_ticks,
+                result.outcome,
+                result.seats.join(";"),
+                result.winners.join(";")
+            );
+        }
+        games.push(result);
+    }
+
+    let summary = summarize(&games);
+    match options.format {
+        Format::Csv => {
+            eprintln!(
+                "{} games, {} unfinished, {:.1} ticks on average",
+                summary.nb_games, summary.nb_unfinished, summary.average_ticks
+            );
+            for (strategy, stats) in summary.strategies.iter() {
+                eprintln!(
+                    "{} : {} wins out of {} seats, win rate {:.3}",
+                    strategy, stats.nb_wins, stats.nb_seats, stats.win_rate
+                );
+            }
+        }
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string(&Report { summary, games }).expect("failed to jsonize report")
+        ),
+    }
+}
+
+fn parse_options(args: Vec<String>) -> Result<Options, String> {
+    let mut options = Options {
+        nb_games: 100,
+        seed: 0,
+        strategies: vec!["medium".to_string(), "hard".to_string()],
+        nb_players: 0, // one seat per strategy unless given
+        team_size: None,
+        max_ticks: 2000,
+        map_kind: MapKind::Uniform,
+        format: Format::Csv,
+    };
+    let mut args = args.into_iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or(format!("missing value after {}", flag))?;
+        let invalid = |_| format!("invalid value {} for {}", value, flag);
+        match flag.as_str() {
+            "--games" => options.nb_games = value.parse().map_err(invalid)?,
+            "--seed" => options.seed = value.parse().map_err(invalid)?,
+            "--players" => options.nb_players = value.parse().map_err(invalid)?,
+            "--team-size" => options.team_size = Some(value.parse().map_err(invalid)?),
+            "--max-ticks" => options.max_ticks = value.parse().map_err(invalid)?,
+            "--strategies" => {
+                options.strategies = value.split(',').map(|name| name.to_string()).collect()
+            }
+            "--map" => {
+                options.map_kind = match value.as_str() {
+                    "uniform" => MapKind::Uniform,
+                    "ranges" => MapKind::MountainRanges,
+                    "symmetric" => MapKind::Symmetric,
+                    _ => return Err(format!("unknown map {}", value)),
+                }
+            }
+            "--format" => {
+                options.format = match value.as_str() {
+                    "csv" => Format::Csv,
+                    "json" => Format::Json,
+                    _ => return Err(format!("unknown format {}", value)),
+                }
+            }
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    if let Some(name) = options
+        .strategies
+        .iter()
+        .find(|name| name.as_str() != "idle" && name.parse::<BotDifficulty>().is_err())
+    {
+        return Err(format!("unknown strategy {}", name));
+    }
+    if options.nb_players == 0 {
+        options.nb_players = options.strategies.len();
+    }
+    if !(2..=6).contains(&options.nb_players) {
+        return Err("a game is played by 2 to 6 players".to_string());
+    }
+    if options
+        .team_size
+        .is_some_and(|team_size| team_size == 0 || !options.nb_players.is_multiple_of(team_size))
+    {
+        return Err("the players can't be split into teams of this size".to_string());
+    }
+    Ok(options)
+}
+
+// "idle" players never move, a baseline to measure how fast a strategy expands
+fn simulate(seed: u64, seats: Vec<String>, options: &Options) -> GameResult {
+    let map = generate_map(options.map_kind, seed, options.nb_players);
+    let mut engine = GameEngine::new(map.board);
+    // simulated players are not flagged as bots, the engine ends games that only bots play
+    let players: Vec<(String, String, bool)> = seats
+        .iter()
+        .enumerate()
+        .map(|(seat, strategy)| (seat_uuid(seat), format!("{} {}", strategy, seat), false))
+        .collect();
+    let nb_teams = options
+        .team_size
+        .map(|team_size| options.nb_players / team_size);
+    place_players(&mut engine, &map.spawns, &players, nb_teams)
+        .expect("the map has a spawn for every seat");
+    let bots: HashMap<String, BotDifficulty> = seats
+        .iter()
+        .enumerate()
+        .filter_map(|(seat, strategy)| {
+            strategy
+                .parse::<BotDifficulty>()
+                .ok()
+                .map(|difficulty| (seat_uuid(seat), difficulty))
+        })
+        .collect();
+    let inputs = TickInputs {
+        active_players: (0..seats.len()).map(seat_uuid).collect(),
+    };
+    let mut bot_rng = StdRng::seed_from_u64(seed.wrapping_add(2));
+
+    let status = loop {
+        queue_bot_moves(&mut engine, &bots, &mut bot_rng);
+        let outcome = engine.step(&inputs);
+        if outcome.status != GameStatus::Running || engine.tick >= options.max_ticks {
+            break outcome.status;
+        }
+    };
+    let winning_seats: HashSet<usize> = match &status {
+        GameStatus::Won(_) | GameStatus::TeamWon(_) => (0..seats.len())
+            .filter(|seat| !engine.is_eliminated(&seat_uuid(*seat)))
+            .collect(),
+        _ => HashSet::new(),
+    };
+    GameResult {
+        seed,
+        nb_ticks: engine.tick,
+        outcome: match status {
+            GameStatus::Running => "max_ticks",
+            GameStatus::Won(_) => "won",
+            GameStatus::TeamWon(_) => "team_won",
+            GameStatus::NoSurvivor => "no_survivor",
+            GameStatus::NobodyActive => "nobody_active",
+        },
+        winners: (0..seats.len())
+            .filter(|seat| winning_seats.contains(seat))
+            .map(|seat| seats[seat].clone())
+            .collect(),
+        seats,
+    }
+}
+
+fn summarize(games: &[GameResult]) -> Summary {
+    let mut strategies: BTreeMap<String, StrategySummary> = BTreeMap::new();
+    for game in games.iter() {
+        for strategy in game.seats.iter() {
+            strategies.entry(strategy.clone()).or_default().nb_seats += 1;
+        }
+        for strategy in game.winners.iter() {
+            strategies.entry(strategy.clone()).or_default().nb_wins += 1;
+        }
+    }
+    for stats in strategies.values_mut() {
+        stats.win_rate = stats.nb_wins as f64 / stats.nb_seats as f64;
+    }
+    Summary {
+        nb_games: games.len(),
+        nb_unfinished: games
+            .iter()
+            .filter(|game| game.outcome == "max_ticks")
+            .count(),
+        average_ticks: games.iter().map(|game| game.nb_ticks as f64).sum::<f64>()
+            / games.len().max(1) as f64,
+        strategies,
+    }
+}
+
+// Zero padded like the replays, so that the players are resolved in seat order
+fn seat_uuid(seat: usize) -> String {
+    format!("{:03}", seat)
+}
@@ -1,23 +1,30 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet, VecDeque},
     sync::{Arc, RwLock},
 };
 
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rand::Rng;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
 use crate::{
-    constants::{
-        self, MAX_GAME_HEIGHT, MAX_GAME_WIDTH, MIN_GAME_HEIGHT, MIN_GAME_WIDTH, NB_CASTLES,
-    },
-    models::messages_to_clients::WsMessageToClient,
+    constants,
+    models::messages_to_clients::{SpectatorUpdate, TileUpdate, WsMessageToClient},
+    requests::requests::GameMode,
 };
 use crate::{
-    constants::{NB_MOUTAINS, YEAR_2128_TIMESTAMP},
-    service_layer::player_service::Player,
+    constants::YEAR_2128_TIMESTAMP,
+    service_layer::{
+        bot_service::BotDifficulty,
+        game_engine::GameEngine,
+        leaderboard_service::LeaderboardSnapshot,
+        map_generator::{generate_map, GeneratedMap, MapKind},
+        player_service::Player,
+        replay_service::Replay,
+    },
 };
 
 #[derive(Debug)]
@@ -27,35 +34,59 @@ pub struct AppState {
     pub global_chat_messages: RwLock<Vec<ChatMessage>>,
     pub players: RwLock<HashMap<String, Player>>,
     pub lobbies: [RwLock<Lobby>; constants::NB_LOBBIES],
+    pub leaderboards: RwLock<LeaderboardSnapshot>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct ChatMessage {
     pub poster: String,
     pub message: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct LobbyMap {
+    pub map_id: String,
+    pub name: String,
+    pub map: GeneratedMap,
+}
+
 #[derive(Debug)]
 pub struct Lobby {
     pub lobby_id: usize,
     pub status: LobbyStatus,
     pub next_starting_time: i64, // unix timestamp seconds
     pub player_capacity: usize,
+    pub is_ranked: bool,
+    pub team_size: Option<usize>, // none in free for all
+    pub bots_only: bool,          // only bot accounts can join
+    pub map_kind: MapKind,
+    pub bot_fill_delay_sec: Option<i64>, // bots complete the lobby this long after its first player joined, never when none
+    pub spawns: Vec<(usize, usize)>,     // one per seat, drawn along with the board
+    pub custom_map: Option<LobbyMap>, // picked by the players waiting, dropped when they all leave or their game ends
     pub lobby_broadcast: broadcast::Sender<WsMessageToClient>,
-    pub players: HashMap<String, String>, // uuid->name
+    pub players: HashMap<String, String>, // uuid->name, bots included
+    pub bots: HashMap<String, BotDifficulty>, // uuid->difficulty
+    pub awaiting_since: i64,              // unix timestamp seconds, when the first player joined
+    pub disconnected: HashMap<String, i64>, // uuid->deadline to reconnect, unix timestamp seconds
     pub messages: Vec<ChatMessage>,
-    pub board_game: Vec<Vec<Tile>>,
-    pub tick: usize,
+    pub game_engine: GameEngine,
+    pub seed: u64, // only revealed to the players once the game is over
+    pub game_id: String,
+    pub game_started_at: i64, // unix timestamp seconds
+    pub replay: Replay,
+    pub sent_boards: HashMap<String, (usize, Vec<Vec<TileUpdate>>)>, // uuid->(tick, board) last sent, deltas are computed from it
+    pub spectators: HashSet<String>,                                 // uuids
+    pub spectator_frames: VecDeque<SpectatorUpdate>, // frames waiting for the spectator delay
 }
 
-#[derive(Debug, Copy, Clone, Serialize, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Serialize, PartialEq, Eq, JsonSchema)]
 pub enum LobbyStatus {
     AwaitingPlayers,
     InGame,
     StartingSoon, // todo : be careful, if a player join and triggers that status and then leaves + what if multiple people leave
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tile {
     pub status: TileStatus,
     pub tile_type: TileType,
@@ -72,13 +103,13 @@ impl Default for Tile {
         }
     }
 }
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum TileStatus {
     Empty,
     Occupied,
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 pub enum TileType {
     Blank,
     Kingdom,
@@ -87,46 +118,76 @@ pub enum TileType {
 }
 
 impl Lobby {
-    fn new(lobby_id: usize, player_capacity: usize) -> Self {
+    fn new(
+        lobby_id: usize,
+        player_capacity: usize,
+        is_ranked: bool,
+        team_size: Option<usize>,
+        bots_only: bool,
+        map_kind: MapKind,
+        bot_fill_delay_sec: Option<i64>,
+    ) -> Self {
         let mut lobby = Lobby {
             lobby_id,
             status: LobbyStatus::AwaitingPlayers,
             next_starting_time: YEAR_2128_TIMESTAMP,
             player_capacity,
+            is_ranked,
+            team_size,
+            bots_only,
+            map_kind,
+            bot_fill_delay_sec,
+            spawns: vec![],
+            custom_map: None,
             lobby_broadcast: broadcast::channel(10).0,
             players: HashMap::new(),
+            bots: HashMap::new(),
+            awaiting_since: 0,
+            disconnected: HashMap::new(),
             messages: vec![],
-            board_game: vec![],
-            tick: 0,
+            game_engine: GameEngine::new(vec![]),
+            seed: 0,
+            game_id: String::new(),
+            game_started_at: 0,
+            replay: Replay::default(),
+            sent_boards: HashMap::new(),
+            spectators: HashSet::new(),
+            spectator_frames: VecDeque::new(),
         };
         lobby.generate_new_board();
         lobby
     }
+    // A new seed is drawn for every game, the board and the spawns are derived from it
+    // so that any game can be recreated from its seed and map kind
     pub fn generate_new_board(&mut self) {
-        let mut rng = rand::thread_rng();
-        let width = rng.gen_range(MIN_GAME_WIDTH..MAX_GAME_WIDTH);
-        let height = rng.gen_range(MIN_GAME_HEIGHT..MAX_GAME_HEIGHT);
-        self.board_game = vec![];
-        for _ in 0..width {
-            let mut column = vec![];
-            for _ in 0..height {
-                column.push(Tile::default())
-            }
-            self.board_game.push(column)
-        }
-        for _ in 0..NB_MOUTAINS {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            if self.board_game[x][y].tile_type == TileType::Blank {
-                self.board_game[x][y].tile_type = TileType::Mountain;
-            }
+        self.seed = rand::thread_rng().gen();
+        let map = match &self.custom_map {
+            Some(custom_map) => custom_map.map.clone(),
+            None => generate_map(self.map_kind, self.seed, self.player_capacity),
+        };
+        self.game_engine = GameEngine::new(map.board);
+        self.spawns = map.spawns;
+    }
+
+    pub fn mode(&self) -> GameMode {
+        match (self.bots_only, self.team_size) {
+            (true, _) => GameMode::Bots,
+            (false, Some(_)) => GameMode::Teams,
+            (false, None) => GameMode::FreeForAll,
         }
-        for _ in 0..NB_CASTLES {
-            let x = rng.gen_range(0..width);
-            let y = rng.gen_range(0..height);
-            if self.board_game[x][y].tile_type == TileType::Blank {
-                self.board_game[x][y].tile_type = TileType::Castle;
-                self.board_game[x][y].nb_troops = 15;
+    }
+
+    // Bots are only there for the players, they leave along with the last one. Once the game
+    // is starting the bots stay, the game goes on and the lobby is reset when it ends.
+    pub fn remove_player(&mut self, player_uuid: &str) {
+        self.players.remove(player_uuid);
+        if self.status == LobbyStatus::AwaitingPlayers
+            && self.players.keys().all(|uuid| self.bots.contains_key(uuid))
+        {
+            self.players.clear();
+            self.bots.clear();
+            if self.custom_map.take().is_some() {
+                self.generate_new_board();
             }
         }
     }
@@ -140,11 +201,70 @@ impl AppState {
             .build(manager)
             .expect("couldn't create pool");
         let lobbies: [RwLock<Lobby>; constants::NB_LOBBIES] = [
-            RwLock::new(Lobby::new(0, 2)),
-            RwLock::new(Lobby::new(1, 2)),
-            RwLock::new(Lobby::new(2, 2)),
-            RwLock::new(Lobby::new(3, 3)),
-            RwLock::new(Lobby::new(4, 5)),
+            RwLock::new(Lobby::new(
+                0,
+                2,
+                true,
+                None,
+                false,
+                MapKind::Symmetric,
+                Some(60),
+            )),
+            RwLock::new(Lobby::new(
+                1,
+                2,
+                true,
+                None,
+                false,
+                MapKind::Symmetric,
+                Some(60),
+            )),
+            RwLock::new(Lobby::new(
+                2,
+                2,
+                true,
+                None,
+                false,
+                MapKind::Uniform,
+                Some(60),
+            )),
+            RwLock::new(Lobby::new(
+                3,
+                3,
+                true,
+                None,
+                false,
+                MapKind::MountainRanges,
+                Some(60),
+            )),
+            RwLock::new(Lobby::new(
+                4,
+                5,
+                true,
+                None,
+                false,
+                MapKind::Uniform,
+                Some(60),
+            )),
+            RwLock::new(Lobby::new(
+                5,
+                4,
+                false,
+                Some(2),
+                false,
+                MapKind::MountainRanges,
+                Some(60),
+            )), // 2v2
+            RwLock::new(Lobby::new(
+                6,
+                6,
+                false,
+                Some(3),
+                false,
+                MapKind::Uniform,
+                Some(60),
+            )), // 3v3
+            RwLock::new(Lobby::new(7, 4, false, None, true, MapKind::Uniform, None)), // bot accounts, never filled
         ];
         Arc::new(AppState {
             connection: pool,
@@ -152,6 +272,7 @@ impl AppState {
             global_chat_messages: RwLock::new(vec![]),
             players: RwLock::new(HashMap::new()),
             lobbies,
+            leaderboards: RwLock::new(LeaderboardSnapshot::default()),
         })
     }
 }
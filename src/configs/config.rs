@@ -19,3 +19,9 @@ impl Config {
         toml::from_str(&file_content).expect("failed to parse string file into toml")
     }
 }
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,11 +1,14 @@
 pub const DATABASE_NAME: &str = "game.db";
-pub const NB_LOBBIES: usize = 5;
+pub const NB_LOBBIES: usize = 8;
 pub const DELAY_FOR_GAMESTART_SEC: i64 = 3;
+pub const RECONNECT_GRACE_PERIOD_SEC: i64 = 30;
 pub const MINIMUM_PLAYERNAME_LENGTH: usize = 3;
 pub const MAXIMUM_PLAYERNAME_LENGTH: usize = 18;
 pub const MAX_QUEUED_MOVES: usize = 12;
+pub const MAX_QUEUED_PATH_MOVES: usize = 48; // a path can fill the queue further than single moves
 pub const PLAYER_NAMES: [&str; 4] = ["Sylvain", "Risitas", "Shermaine", "June"];
 pub const YEAR_2128_TIMESTAMP: i64 = 5000000000;
+pub const PROTOCOL_VERSION: u8 = 10; // bumped whenever the shape of a message changes
 
 pub const TICK_GAME_INTERVAL_MS: u64 = 500;
 pub const TICK_KINGDOM: usize = 1;
@@ -14,6 +17,14 @@ pub const TICK_BLANK: usize = 10;
 
 pub const NB_MOUTAINS: usize = 35;
 pub const NB_CASTLES: usize = 15;
+pub const NB_MOUNTAIN_RANGES: usize = 5;
+pub const CASTLE_GARRISON: usize = 15;
+
+pub const MIN_SPAWN_DISTANCE: usize = 9; // walking distance between two kingdoms
+pub const CASTLE_FAIRNESS_RADIUS: usize = 6; // castles within this walking distance of a spawn are compared
+pub const MAX_CASTLE_IMBALANCE: usize = 1;
+pub const MAX_MAP_ATTEMPTS: usize = 10; // maps drawn before falling back to an open board
+pub const MAX_MAP_NAME_LENGTH: usize = 32;
 
 pub const MIN_GAME_WIDTH: usize = 18;
 pub const MAX_GAME_WIDTH: usize = 23;
@@ -21,3 +32,21 @@ pub const MIN_GAME_HEIGHT: usize = 18;
 pub const MAX_GAME_HEIGHT: usize = 23;
 
 pub const DISPLAY_N_LAST_MESSAGES: usize = 3;
+
+pub const EASY_BOT_TICK_INTERVAL: usize = 3; // easy bots only play one tick out of 3
+pub const BOT_PATH_LENGTH: usize = 8; // bots queue at most this many moves of a path at once
+pub const BOT_API_KEY_LENGTH: usize = 32;
+pub const BOT_API_KEY_HEADER: &str = "x-api-key"; // bot accounts send their api key in this header
+pub const BOT_MAX_COMMANDS_PER_TICK: usize = 5; // commands of a bot account beyond that are rejected until the next tick
+
+pub const SPECTATOR_DELAY_TICKS: usize = 10; // spectators see the board as it was 5 seconds ago, 0 for live
+
+pub const DEFAULT_PAGE_SIZE: usize = 20;
+pub const MAX_PAGE_SIZE: usize = 100;
+
+pub const INITIAL_RATING: i64 = 1200;
+pub const RATING_K_FACTOR: f64 = 32.0;
+
+pub const LEADERBOARD_SIZE: usize = 100;
+pub const LEADERBOARD_REFRESH_INTERVAL_SEC: u64 = 60;
+pub const LEADERBOARD_MIN_GAMES_FOR_WIN_RATE: usize = 5;
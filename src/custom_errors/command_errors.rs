@@ -0,0 +1,59 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+// Why a websocket command was rejected, sent back to the player who issued it
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandError {
+    InvalidCommand,
+    UnsupportedVersion,
+    UnknownLobby,
+    LobbyFull,
+    LobbyAlreadyStarted,
+    AlreadyInLobby,
+    ReservedToBots,
+    ReservedToPlayers,
+    NotInLobby,
+    NotInGame,
+    QueueFull,
+    NoSuchMove,
+    OutOfBoard,
+    TileNotOwned,
+    NoPath,
+    CannotSpectate,
+    NotSpectating,
+    RateLimited,
+    UnknownMap,
+    MapTooSmall,
+    RankedLobby,
+}
+
+impl CommandError {
+    pub fn error_message(&self) -> String {
+        match self {
+            Self::InvalidCommand => "Command could not be parsed".to_string(),
+            Self::UnsupportedVersion => "Protocol version not supported".to_string(),
+            Self::UnknownLobby => "Lobby does not exist".to_string(),
+            Self::LobbyFull => "Lobby is full".to_string(),
+            Self::LobbyAlreadyStarted => "Lobby already started".to_string(),
+            Self::AlreadyInLobby => "Already in this lobby".to_string(),
+            Self::ReservedToBots => "Lobby is reserved to bot accounts".to_string(),
+            Self::ReservedToPlayers => "Bot accounts can only join bot lobbies".to_string(),
+            Self::NotInLobby => "Not in a lobby".to_string(),
+            Self::NotInGame => "Not playing in a running game".to_string(),
+            Self::QueueFull => "Too many queued moves".to_string(),
+            Self::NoSuchMove => "No queued move at this position".to_string(),
+            Self::OutOfBoard => "Tile is out of the board".to_string(),
+            Self::TileNotOwned => "Tile is not owned by the player".to_string(),
+            Self::NoPath => "No known path to the tile".to_string(),
+            Self::CannotSpectate => {
+                "Only running games of other players can be watched".to_string()
+            }
+            Self::NotSpectating => "Not spectating any game".to_string(),
+            Self::RateLimited => "Too many commands during this tick".to_string(),
+            Self::UnknownMap => "Map does not exist".to_string(),
+            Self::MapTooSmall => "Map has fewer spawns than the lobby has seats".to_string(),
+            Self::RankedLobby => "Custom maps can't be played in ranked lobbies".to_string(),
+        }
+    }
+}
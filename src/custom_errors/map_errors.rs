@@ -0,0 +1,35 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+// Why a map can't be played
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MapError {
+    InvalidName,
+    InvalidDimensions,
+    UnknownTile,
+    InvalidGarrison,
+    NotEnoughSpawns,
+    InvalidSpawn,
+    SpawnUnreachable,
+    SpawnsTooClose,
+    UnfairCastles,
+}
+
+impl MapError {
+    pub fn error_message(&self) -> String {
+        match self {
+            Self::InvalidName => "Map name is empty or too long".to_string(),
+            Self::InvalidDimensions => {
+                "Map size is out of bounds or doesn't match its rows".to_string()
+            }
+            Self::UnknownTile => "Rows can only contain '.', '#', 'C' and 'S'".to_string(),
+            Self::InvalidGarrison => "Garrisons must be placed on castles".to_string(),
+            Self::NotEnoughSpawns => "Not enough spawns for the players".to_string(),
+            Self::InvalidSpawn => "Spawns must be distinct blank tiles of the board".to_string(),
+            Self::SpawnUnreachable => "A spawn can't be reached from the others".to_string(),
+            Self::SpawnsTooClose => "Two spawns are too close to each other".to_string(),
+            Self::UnfairCastles => "Spawns don't have as many castles around them".to_string(),
+        }
+    }
+}
@@ -1,2 +1,4 @@
+pub mod command_errors;
+pub mod map_errors;
 pub mod service_errors;
 pub mod sqlite_errors;
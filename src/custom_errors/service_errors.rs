@@ -1,28 +1,33 @@
-use crate::custom_errors::sqlite_errors::SqliteError;
+use crate::custom_errors::{map_errors::MapError, sqlite_errors::SqliteError};
 use axum::{
     http::StatusCode,
     response::{IntoResponse, Response},
     Json,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct ApiResponseError {
     pub error_message: String,
     pub error_code: ErrorCode,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub enum ServiceError {
     Internal,
+    NotFound,
     PlayerAlreadyExist,
+    InvalidPlayername(String), // why the name was refused
     Sqlite(SqliteError),
     ForbiddenQuery,
     Transaction,
+    InvalidMap(MapError),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub enum ErrorCode {
     NoError = 0,
     UnspecifiedError = 1,
@@ -32,20 +37,26 @@ impl ServiceError {
     pub fn error_message(&self) -> String {
         match self {
             Self::Internal => "Internal error".to_string(),
+            Self::NotFound => "Resource not found".to_string(),
             Self::PlayerAlreadyExist => "Player already exists".to_string(),
+            Self::InvalidPlayername(reason) => format!("Invalid player name : {}", reason),
             Self::Sqlite(_) => "Sqlite internal error".to_string(),
             Self::ForbiddenQuery => "Query forbidden error".to_string(),
             Self::Transaction => "Transaction error".to_string(),
+            Self::InvalidMap(map_error) => map_error.error_message(),
         }
     }
 
     fn status_code(&self) -> StatusCode {
         match *self {
             Self::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound => StatusCode::NOT_FOUND,
             Self::PlayerAlreadyExist => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::InvalidPlayername(_) => StatusCode::UNPROCESSABLE_ENTITY,
             Self::Sqlite(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ForbiddenQuery => StatusCode::FORBIDDEN,
             Self::Transaction => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::InvalidMap(_) => StatusCode::UNPROCESSABLE_ENTITY,
         }
     }
 }
@@ -54,7 +54,10 @@ pub fn transaction_error(e: rusqlite::Error) -> ServiceError {
 }
 
 impl From<SqliteError> for ServiceError {
-    fn from(_error: SqliteError) -> Self {
-        ServiceError::Internal
+    fn from(error: SqliteError) -> Self {
+        match error {
+            SqliteError::NotFound => ServiceError::NotFound,
+            _ => ServiceError::Internal,
+        }
     }
 }
@@ -0,0 +1,68 @@
+use rusqlite::params;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::configs::app_state::AppState;
+use crate::custom_errors::service_errors::ServiceError;
+use crate::custom_errors::sqlite_errors::{map_sqlite_error, transaction_error, SqliteError};
+use crate::data_access_layer::player_dal::Player;
+use crate::requests::requests::CreateBotAccountRequest;
+
+// A bot account is a player along with the hash of its api key, created in one go
+pub fn create_bot_account(
+    db: &Arc<AppState>,
+    bot: CreateBotAccountRequest,
+) -> Result<String, ServiceError> {
+    let mut binding = db.connection.get().unwrap();
+    let transaction = binding.transaction().map_err(transaction_error)?;
+    let uuid = Uuid::now_v7().to_string();
+    transaction
+        .execute(
+            "INSERT INTO Players (uuid, name) VALUES (?, ?)",
+            params![uuid, bot.name],
+        )
+        .map_err(transaction_error)?;
+    transaction
+        .execute(
+            "INSERT INTO BotAccounts (player_uuid, api_key_hash) VALUES (?, ?)",
+            params![uuid, bot.api_key_hash],
+        )
+        .map_err(transaction_error)?;
+    transaction.commit().map_err(transaction_error)?;
+
+    Ok(uuid)
+}
+
+pub fn get_bot_by_api_key_hash(
+    db: &Arc<AppState>,
+    api_key_hash: String,
+) -> Result<Player, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached(
+            "SELECT Players.* FROM BotAccounts JOIN Players ON Players.uuid = BotAccounts.player_uuid
+            WHERE BotAccounts.api_key_hash = ? LIMIT 1",
+        )
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row(params![api_key_hash], |row| {
+            Ok(Player {
+                uuid: row.get("uuid")?,
+                name: row.get("name")?,
+            })
+        })
+        .map_err(map_sqlite_error)
+}
+
+pub fn is_bot_account(db: &Arc<AppState>, player_uuid: String) -> Result<bool, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT COUNT(*) FROM BotAccounts WHERE player_uuid = ?")
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row(params![player_uuid], |row| row.get::<_, usize>(0))
+        .map(|count| count > 0)
+        .map_err(map_sqlite_error)
+}
@@ -0,0 +1,138 @@
+use rusqlite::params;
+use std::sync::Arc;
+
+use crate::configs::app_state::AppState;
+use crate::custom_errors::service_errors::ServiceError;
+use crate::custom_errors::sqlite_errors::{map_sqlite_error, transaction_error, SqliteError};
+use crate::requests::requests::{self, GamePlayerResponse, GameResponse, PlayerGameResponse};
+
+pub fn create_game(
+    db: &Arc<AppState>,
+    game: requests::CreateGameRequest,
+) -> Result<(), ServiceError> {
+    let mut binding = db.connection.get().unwrap();
+    let transaction = binding.transaction().map_err(transaction_error)?;
+    transaction
+        .execute(
+            "INSERT INTO Games (game_id, lobby_id, player_capacity, mode, started_at, ended_at, nb_ticks, winner_name, winning_team) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                game.game_id,
+                game.lobby_id,
+                game.player_capacity,
+                game.mode.as_str(),
+                game.started_at,
+                game.ended_at,
+                game.nb_ticks,
+                game.winner_name,
+                game.winning_team
+            ],
+        )
+        .map_err(transaction_error)?;
+    for player in game.players {
+        transaction
+            .execute(
+                "INSERT INTO GamePlayers (game_id, player_uuid, player_name, placement, total_positions, total_troops, team) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    game.game_id,
+                    player.player_uuid,
+                    player.player_name,
+                    player.placement,
+                    player.total_positions,
+                    player.total_troops,
+                    player.team
+                ],
+            )
+            .map_err(transaction_error)?;
+    }
+    transaction.commit().map_err(transaction_error)
+}
+
+pub fn get_game(db: &Arc<AppState>, game_id: String) -> Result<GameResponse, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT * FROM Games WHERE game_id = ? LIMIT 1")
+        .map_err(map_sqlite_error)?;
+    let mut game = statement
+        .query_row(params![game_id], |row| {
+            Ok(GameResponse {
+                game_id: row.get("game_id")?,
+                lobby_id: row.get("lobby_id")?,
+                player_capacity: row.get("player_capacity")?,
+                started_at: row.get("started_at")?,
+                ended_at: row.get("ended_at")?,
+                nb_ticks: row.get("nb_ticks")?,
+                winner_name: row.get("winner_name")?,
+                winning_team: row.get("winning_team")?,
+                players: vec![],
+            })
+        })
+        .map_err(map_sqlite_error)?;
+
+    let mut statement = binding
+        .prepare_cached("SELECT * FROM GamePlayers WHERE game_id = ? ORDER BY placement")
+        .map_err(map_sqlite_error)?;
+    game.players = statement
+        .query_map(params![game_id], |row| {
+            Ok(GamePlayerResponse {
+                player_name: row.get("player_name")?,
+                placement: row.get("placement")?,
+                total_positions: row.get("total_positions")?,
+                total_troops: row.get("total_troops")?,
+                team: row.get("team")?,
+            })
+        })
+        .map_err(map_sqlite_error)?
+        .collect::<Result<Vec<GamePlayerResponse>, rusqlite::Error>>()
+        .map_err(map_sqlite_error)?;
+
+    Ok(game)
+}
+
+pub fn get_player_games(
+    db: &Arc<AppState>,
+    player_uuid: String,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<PlayerGameResponse>, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached(
+            "SELECT Games.*, GamePlayers.placement, GamePlayers.total_positions, GamePlayers.total_troops
+            FROM GamePlayers JOIN Games ON Games.game_id = GamePlayers.game_id
+            WHERE GamePlayers.player_uuid = ?
+            ORDER BY Games.ended_at DESC LIMIT ? OFFSET ?",
+        )
+        .map_err(map_sqlite_error)?;
+
+    let games = statement
+        .query_map(params![player_uuid, limit, offset], |row| {
+            Ok(PlayerGameResponse {
+                game_id: row.get("game_id")?,
+                lobby_id: row.get("lobby_id")?,
+                player_capacity: row.get("player_capacity")?,
+                started_at: row.get("started_at")?,
+                ended_at: row.get("ended_at")?,
+                nb_ticks: row.get("nb_ticks")?,
+                winner_name: row.get("winner_name")?,
+                winning_team: row.get("winning_team")?,
+                placement: row.get("placement")?,
+                total_positions: row.get("total_positions")?,
+                total_troops: row.get("total_troops")?,
+            })
+        })
+        .map_err(map_sqlite_error)?
+        .collect::<Result<Vec<PlayerGameResponse>, rusqlite::Error>>()
+        .map_err(map_sqlite_error);
+    games
+}
+
+pub fn count_player_games(db: &Arc<AppState>, player_uuid: String) -> Result<usize, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT COUNT(*) FROM GamePlayers WHERE player_uuid = ?")
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row(params![player_uuid], |row| row.get(0))
+        .map_err(map_sqlite_error)
+}
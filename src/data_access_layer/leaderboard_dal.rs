@@ -0,0 +1,82 @@
+use rusqlite::params;
+use std::sync::Arc;
+
+use crate::configs::app_state::AppState;
+use crate::constants::INITIAL_RATING;
+use crate::custom_errors::sqlite_errors::{map_sqlite_error, SqliteError};
+use crate::requests::requests::{GameMode, LeaderboardEntry, LeaderboardSort};
+
+// Aggregates the games ended since `since`, optionally restricted to one mode and lobby capacity.
+// Ratings are global, only the players who played in the window are listed.
+pub fn get_leaderboard(
+    db: &Arc<AppState>,
+    since: i64,
+    mode: Option<GameMode>,
+    player_capacity: Option<usize>,
+    sort_by: LeaderboardSort,
+    min_games: usize,
+    limit: usize,
+) -> Result<Vec<LeaderboardEntry>, SqliteError> {
+    let order_by = match sort_by {
+        LeaderboardSort::Rating => "rating DESC, wins DESC",
+        LeaderboardSort::Wins => "wins DESC, games_played ASC",
+        LeaderboardSort::WinRate => "CAST(wins AS REAL) / games_played DESC, games_played DESC",
+        LeaderboardSort::GamesPlayed => "games_played DESC, wins DESC",
+    };
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached(&format!(
+            "SELECT Players.name AS player_name,
+                COALESCE(Ratings.rating, ?1) AS rating,
+                COUNT(*) AS games_played,
+                SUM(COALESCE(GamePlayers.team = Games.winning_team, FALSE)
+                    OR (Games.winner_name IS NOT NULL AND GamePlayers.placement = 1)) AS wins
+            FROM GamePlayers
+            JOIN Games ON Games.game_id = GamePlayers.game_id
+            JOIN Players ON Players.uuid = GamePlayers.player_uuid
+            LEFT JOIN Ratings ON Ratings.player_uuid = GamePlayers.player_uuid
+            WHERE Games.ended_at >= ?2
+                AND (?3 IS NULL OR Games.mode = ?3)
+                AND (?4 IS NULL OR Games.player_capacity = ?4)
+            GROUP BY GamePlayers.player_uuid
+            HAVING games_played >= ?5
+            ORDER BY {} LIMIT ?6",
+            order_by
+        ))
+        .map_err(map_sqlite_error)?;
+
+    let entries = statement
+        .query_map(
+            params![
+                INITIAL_RATING,
+                since,
+                mode.map(|mode| mode.as_str()),
+                player_capacity,
+                min_games,
+                limit
+            ],
+            |row| {
+                let games_played: usize = row.get("games_played")?;
+                let wins: usize = row.get("wins")?;
+                Ok(LeaderboardEntry {
+                    rank: 0,
+                    player_name: row.get("player_name")?,
+                    rating: row.get("rating")?,
+                    games_played,
+                    wins,
+                    win_rate: wins as f64 / games_played as f64,
+                })
+            },
+        )
+        .map_err(map_sqlite_error)?
+        .enumerate()
+        .map(|(i, entry)| {
+            entry.map(|entry| LeaderboardEntry {
+                rank: i + 1,
+                ..entry
+            })
+        })
+        .collect::<Result<Vec<LeaderboardEntry>, rusqlite::Error>>()
+        .map_err(map_sqlite_error);
+    entries
+}
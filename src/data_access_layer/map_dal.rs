@@ -0,0 +1,80 @@
+use rusqlite::params;
+use std::sync::Arc;
+
+use crate::configs::app_state::AppState;
+use crate::custom_errors::sqlite_errors::{map_sqlite_error, SqliteError};
+use crate::requests::requests::{CreateMapRequest, MapResponse};
+
+pub fn create_map(db: &Arc<AppState>, map: CreateMapRequest) -> Result<(), SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached(
+            "INSERT INTO Maps (map_id, name, width, height, nb_spawns, created_at, data)
+            VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .map_err(map_sqlite_error)?;
+    statement
+        .execute(params![
+            map.map_id,
+            map.name,
+            map.width,
+            map.height,
+            map.nb_spawns,
+            map.created_at,
+            map.data
+        ])
+        .map_err(map_sqlite_error)?;
+    Ok(())
+}
+
+pub fn get_maps(
+    db: &Arc<AppState>,
+    limit: usize,
+    offset: usize,
+) -> Result<Vec<MapResponse>, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached(
+            "SELECT map_id, name, width, height, nb_spawns, created_at FROM Maps
+            ORDER BY created_at DESC LIMIT ? OFFSET ?",
+        )
+        .map_err(map_sqlite_error)?;
+
+    let maps = statement
+        .query_map(params![limit, offset], |row| {
+            Ok(MapResponse {
+                map_id: row.get("map_id")?,
+                name: row.get("name")?,
+                width: row.get("width")?,
+                height: row.get("height")?,
+                nb_spawns: row.get("nb_spawns")?,
+                created_at: row.get("created_at")?,
+            })
+        })
+        .map_err(map_sqlite_error)?
+        .collect::<Result<Vec<MapResponse>, rusqlite::Error>>()
+        .map_err(map_sqlite_error);
+    maps
+}
+
+pub fn count_maps(db: &Arc<AppState>) -> Result<usize, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT COUNT(*) FROM Maps")
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row([], |row| row.get(0))
+        .map_err(map_sqlite_error)
+}
+
+pub fn get_map_data(db: &Arc<AppState>, map_id: String) -> Result<Vec<u8>, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT data FROM Maps WHERE map_id = ? LIMIT 1")
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row(params![map_id], |row| row.get("data"))
+        .map_err(map_sqlite_error)
+}
@@ -1 +1,7 @@
+pub mod bot_account_dal;
+pub mod game_dal;
+pub mod leaderboard_dal;
+pub mod map_dal;
 pub mod player_dal;
+pub mod rating_dal;
+pub mod replay_dal;
@@ -0,0 +1,48 @@
+use rusqlite::params;
+use std::sync::Arc;
+
+use crate::configs::app_state::AppState;
+use crate::custom_errors::service_errors::ServiceError;
+use crate::custom_errors::sqlite_errors::{map_sqlite_error, transaction_error, SqliteError};
+use crate::requests::requests::SaveRatingRequest;
+
+pub fn get_rating(db: &Arc<AppState>, player_uuid: String) -> Result<i64, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT rating FROM Ratings WHERE player_uuid = ? LIMIT 1")
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row(params![player_uuid], |row| row.get("rating"))
+        .map_err(map_sqlite_error)
+}
+
+pub fn save_ratings(
+    db: &Arc<AppState>,
+    game_id: String,
+    ratings: &[SaveRatingRequest],
+) -> Result<(), ServiceError> {
+    let mut binding = db.connection.get().unwrap();
+    let transaction = binding.transaction().map_err(transaction_error)?;
+    for rating in ratings {
+        transaction
+            .execute(
+                "INSERT INTO Ratings (player_uuid, rating, nb_rated_games) VALUES (?, ?, 1)
+                ON CONFLICT(player_uuid) DO UPDATE SET rating = excluded.rating, nb_rated_games = nb_rated_games + 1",
+                params![rating.player_uuid, rating.rating_after],
+            )
+            .map_err(transaction_error)?;
+        transaction
+            .execute(
+                "INSERT INTO RatingHistory (game_id, player_uuid, rating_before, rating_after) VALUES (?, ?, ?, ?)",
+                params![
+                    game_id,
+                    rating.player_uuid,
+                    rating.rating_before,
+                    rating.rating_after
+                ],
+            )
+            .map_err(transaction_error)?;
+    }
+    transaction.commit().map_err(transaction_error)
+}
@@ -0,0 +1,32 @@
+use rusqlite::params;
+use std::sync::Arc;
+
+use crate::configs::app_state::AppState;
+use crate::custom_errors::sqlite_errors::{map_sqlite_error, SqliteError};
+
+pub fn create_replay(
+    db: &Arc<AppState>,
+    game_id: String,
+    lobby_id: usize,
+    data: Vec<u8>,
+) -> Result<(), SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("INSERT INTO Replays (game_id, lobby_id, data) VALUES (?, ?, ?)")
+        .map_err(map_sqlite_error)?;
+    statement
+        .execute(params![game_id, lobby_id, data])
+        .map_err(map_sqlite_error)?;
+    Ok(())
+}
+
+pub fn get_replay_data(db: &Arc<AppState>, game_id: String) -> Result<Vec<u8>, SqliteError> {
+    let binding = db.connection.get().unwrap();
+    let mut statement = binding
+        .prepare_cached("SELECT data FROM Replays WHERE game_id = ? LIMIT 1")
+        .map_err(map_sqlite_error)?;
+
+    statement
+        .query_row(params![game_id], |row| row.get("data"))
+        .map_err(map_sqlite_error)
+}
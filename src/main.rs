@@ -1,15 +1,6 @@
-mod configs;
-mod constants;
-mod custom_errors;
-mod data_access_layer;
-mod models;
-mod requests;
-mod service_layer;
-mod utilities;
-
 use axum::{
-    extract::{ws::WebSocketUpgrade, Path, State},
-    http::{self, HeaderValue, Method, Response},
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
+    http::{self, HeaderMap, HeaderValue, Method, Response},
     response::IntoResponse,
     routing::{get, post, put},
     Router,
@@ -19,12 +10,15 @@ use tower_http::cors::CorsLayer;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
-use crate::{data_access_layer::player_dal, service_layer::websocket_service::handle_websocket};
+use backend::{
+    configs,
+    constants::BOT_API_KEY_HEADER,
+    data_access_layer::{bot_account_dal, player_dal},
+    requests::requests::WsConnectionRequest,
+    service_layer::{self, bot_account_service::hash_api_key, websocket_service::handle_websocket},
+};
 
 // todo : reference instead of .clone()
-// todo : surrender
-// todo : games history
-// todo : manual queue pointer update
 #[tokio::main]
 async fn main() {
     println!("Hello, world!");
@@ -34,6 +28,12 @@ async fn main() {
 
     let app = Router::new()
         .route("/ws/:player_uuid", get(websocket_connection))
+        .route("/bots/ws", get(bot_websocket_connection))
+        .route(
+            "/bots/new",
+            post(service_layer::bot_account_service::create_bot_account),
+        )
+        .route("/schema", get(service_layer::schema_service::get_schema))
         .route(
             "/players/new",
             get(service_layer::player_service::request_new_player),
@@ -50,6 +50,31 @@ async fn main() {
             "/players/:uuid",
             put(service_layer::player_service::set_playername),
         )
+        .route(
+            "/players/:uuid/games",
+            get(service_layer::game_history_service::get_player_games),
+        )
+        .route(
+            "/games/:game_id",
+            get(service_layer::game_history_service::get_game),
+        )
+        .route(
+            "/leaderboards",
+            get(service_layer::leaderboard_service::get_leaderboard),
+        )
+        .route(
+            "/replays/:game_id",
+            get(service_layer::replay_service::get_replay),
+        )
+        .route(
+            "/replays/:game_id/ticks/:tick",
+            get(service_layer::replay_service::get_replay_tick),
+        )
+        .route(
+            "/maps",
+            get(service_layer::map_service::get_maps).post(service_layer::map_service::upload_map),
+        )
+        .route("/maps/:map_id", get(service_layer::map_service::get_map))
         .layer(
             CorsLayer::new()
                 .allow_origin(
@@ -68,6 +93,7 @@ async fn main() {
                     http::header::AUTHORIZATION,
                     http::header::ACCEPT,
                     http::header::HeaderName::from_lowercase(b"trace").unwrap(),
+                    http::header::HeaderName::from_static(BOT_API_KEY_HEADER),
                 ])
                 .allow_methods(vec![
                     Method::GET,
@@ -83,6 +109,10 @@ async fn main() {
         .await
         .unwrap();
 
+    let cloned_state = app_state.clone();
+    tokio::spawn(async {
+        service_layer::leaderboard_service::leaderboard_loop(cloned_state).await
+    });
     tokio::spawn(async { service_layer::game_service::game_loop(app_state).await });
     axum::serve(listener, app).await.unwrap();
 }
@@ -91,8 +121,12 @@ async fn websocket_connection(
     ws: WebSocketUpgrade,
     State(state): State<Arc<configs::app_state::AppState>>,
     Path(player_uuid): Path<String>,
+    Query(connection): Query<WsConnectionRequest>,
 ) -> impl IntoResponse {
-    println!("new connection {:?}", player_uuid);
+    println!(
+        "new connection {:?} using {:?}",
+        player_uuid, connection.protocol
+    );
 
     let player_in_db = player_dal::get_player_by_uuid(&state, player_uuid.clone());
     if let Err(err) = player_in_db {
@@ -104,6 +138,16 @@ async fn websocket_connection(
     }
 
     let player = player_in_db.unwrap();
+    if !matches!(
+        bot_account_dal::is_bot_account(&state, player.uuid.clone()),
+        Ok(false)
+    ) {
+        println!(
+            "websocket_connection, player uuid={} is a bot account, it has to use its api key",
+            player_uuid
+        );
+        return Response::default();
+    }
     if state
         .players
         .read()
@@ -118,5 +162,48 @@ async fn websocket_connection(
         return Response::default();
     }
 
-    ws.on_upgrade(|socket| handle_websocket(player, socket, state))
+    ws.on_upgrade(move |socket| handle_websocket(player, socket, state, connection.protocol, false))
+}
+
+async fn bot_websocket_connection(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<configs::app_state::AppState>>,
+    Query(connection): Query<WsConnectionRequest>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    // the key goes in a header rather than in the url, urls end up in the logs
+    let Some(api_key) = headers
+        .get(BOT_API_KEY_HEADER)
+        .and_then(|api_key| api_key.to_str().ok())
+    else {
+        println!("bot_websocket_connection, missing api key");
+        return Response::default();
+    };
+    let bot_in_db = bot_account_dal::get_bot_by_api_key_hash(&state, hash_api_key(api_key));
+    let Ok(bot) = bot_in_db else {
+        println!(
+            "bot_websocket_connection, unknown api key, err={:?}",
+            bot_in_db
+        );
+        return Response::default();
+    };
+    println!(
+        "new bot connection {:?} using {:?}",
+        bot.uuid, connection.protocol
+    );
+    if state
+        .players
+        .read()
+        .expect("failed to read players")
+        .get(&bot.uuid)
+        .is_some()
+    {
+        println!(
+            "bot_websocket_connection, bot uuid={} already connected",
+            bot.uuid
+        );
+        return Response::default();
+    }
+
+    ws.on_upgrade(move |socket| handle_websocket(bot, socket, state, connection.protocol, true))
 }
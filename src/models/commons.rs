@@ -0,0 +1,12 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+// Encoding of the websocket frames, picked by the client when connecting
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WsProtocol {
+    #[default]
+    Text, // "/command <json>" text frames
+    Binary, // version byte followed by the MessagePack encoded message
+    Json,   // {"version": PROTOCOL_VERSION, "type": "gameUpdate", "data": {..}} text frames
+}
@@ -1,12 +1,70 @@
-use crate::service_layer::player_service::PlayerMove;
+use schemars::JsonSchema;
+use serde::Deserialize;
 
-#[derive(Debug)]
+use crate::{
+    constants::PROTOCOL_VERSION,
+    custom_errors::command_errors::CommandError,
+    service_layer::{bot_service::BotDifficulty, player_service::PlayerMove},
+};
+
+// Tagged the same way as the messages to the clients : {"type": "joinLobby", "data": 0}
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
 pub enum ClientCommand {
     Move(PlayerMove),
+    Select(usize, usize),
+    Goto(usize, usize),
+    ClearMoves,
+    UndoMove,
+    CancelMovesFrom(usize), // index in the queue
     JoinLobby(usize),
+    InviteBot(BotDifficulty), // into the lobby the player is waiting in
+    UseMap(Option<String>),   // map id, none goes back to generated maps
     SendGlobalMessage(String),
     SendLobbyMessage(String),
     Ping,
+    Surrender,
+    Resync,
+    Spectate(usize),
+    StopSpectating,
+}
+
+// Json protocol : the command along with the protocol version and an optional
+// request id, echoed back if the command is rejected
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ClientRequest {
+    pub version: Option<u8>,
+    pub request_id: Option<String>,
+    #[serde(flatten)]
+    pub command: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ClientCommand {
+    // Frames made for another version of the protocol are rejected
+    pub fn from_binary(frame: &[u8]) -> Result<ClientCommand, CommandError> {
+        match frame.split_first() {
+            Some((&PROTOCOL_VERSION, message)) => {
+                rmp_serde::from_slice(message).map_err(|_| CommandError::InvalidCommand)
+            }
+            _ => Err(CommandError::UnsupportedVersion),
+        }
+    }
+
+    // Returns the request id along with the command, so that a rejection can refer to it
+    pub fn from_json(text: &str) -> (Option<String>, Result<ClientCommand, CommandError>) {
+        let request: ClientRequest = match serde_json::from_str(text) {
+            Ok(request) => request,
+            Err(_) => return (None, Err(CommandError::InvalidCommand)),
+        };
+        if request.version != Some(PROTOCOL_VERSION) {
+            return (request.request_id, Err(CommandError::UnsupportedVersion));
+        }
+        (
+            request.request_id,
+            serde_json::from_value(serde_json::Value::Object(request.command))
+                .map_err(|_| CommandError::InvalidCommand),
+        )
+    }
 }
 
 impl std::str::FromStr for ClientCommand {
@@ -20,11 +78,39 @@ impl std::str::FromStr for ClientCommand {
                     Ok(new_move) => Ok(ClientCommand::Move(new_move)),
                     Err(_) => Err(()),
                 },
+                "/select" => {
+                    let (x, y) = parse_coordinates(commands.next().ok_or(())?)?;
+                    Ok(ClientCommand::Select(x, y))
+                }
+                "/goto" => {
+                    let (x, y) = parse_coordinates(commands.next().ok_or(())?)?;
+                    Ok(ClientCommand::Goto(x, y))
+                }
+                "/clearMoves" => Ok(ClientCommand::ClearMoves),
+                "/undoMove" => Ok(ClientCommand::UndoMove),
+                "/cancelMovesFrom" => match commands.next().ok_or(())?.parse::<usize>() {
+                    Ok(index) => Ok(ClientCommand::CancelMovesFrom(index)),
+                    Err(_) => Err(()),
+                },
                 "/joinLobby" => match commands.next().ok_or(())?.parse::<usize>() {
                     Ok(lob) => Ok(ClientCommand::JoinLobby(lob)),
                     Err(_) => Err(()),
                 },
+                "/inviteBot" => match commands.next().ok_or(())?.parse::<BotDifficulty>() {
+                    Ok(difficulty) => Ok(ClientCommand::InviteBot(difficulty)),
+                    Err(_) => Err(()),
+                },
+                "/useMap" => Ok(ClientCommand::UseMap(
+                    commands.next().map(|map_id| map_id.trim().to_string()),
+                )),
+                "/spectate" => match commands.next().ok_or(())?.parse::<usize>() {
+                    Ok(lob) => Ok(ClientCommand::Spectate(lob)),
+                    Err(_) => Err(()),
+                },
+                "/stopSpectating" => Ok(ClientCommand::StopSpectating),
                 "/ping" => Ok(ClientCommand::Ping),
+                "/surrender" => Ok(ClientCommand::Surrender),
+                "/resync" => Ok(ClientCommand::Resync),
                 "/sendGlobalMessage" => {
                     let new_message = commands.next().ok_or(())?;
                     Ok(ClientCommand::SendGlobalMessage(new_message.to_string()))
@@ -40,3 +126,22 @@ impl std::str::FromStr for ClientCommand {
         }
     }
 }
+
+// "x y"
+fn parse_coordinates(input: &str) -> Result<(usize, usize), ()> {
+    let mut coordinates = input.split_whitespace();
+    let x = coordinates
+        .next()
+        .ok_or(())?
+        .parse::<usize>()
+        .map_err(|_| ())?;
+    let y = coordinates
+        .next()
+        .ok_or(())?
+        .parse::<usize>()
+        .map_err(|_| ())?;
+    match coordinates.next() {
+        None => Ok((x, y)),
+        Some(_) => Err(()),
+    }
+}
@@ -1,32 +1,75 @@
-use std::{any::Any, collections::HashMap, sync::Arc};
+use std::collections::HashMap;
 
 use axum::extract::ws::Message;
+use schemars::JsonSchema;
 use serde::Serialize;
 
 use crate::{
-    configs::{
-        self,
-        app_state::{ChatMessage, LobbyStatus, Tile, TileStatus, TileType},
+    configs::app_state::{ChatMessage, LobbyStatus, Tile, TileStatus, TileType},
+    constants::PROTOCOL_VERSION,
+    custom_errors::command_errors::CommandError,
+    models::commons::WsProtocol,
+    service_layer::{
+        map_generator::MapKind,
+        player_service::{Color, PlayerMove, PlayerMoves},
     },
-    service_layer::player_service::{Color, PlayerMoves},
 };
 
-#[derive(Debug, Clone)]
+// Tagged as {"type": "gameStarted", "data": 0}, the types match the verbs of the text protocol
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
 pub enum WsMessageToClient {
     Pong,
+    #[serde(rename = "lobbyJoined")]
     JoinLobby(usize),
+    #[serde(rename = "lobbiesGeneralUpdate")]
     LobbiesUpdate(LobbiesGeneralUpdate),
     GlobalChatSync(Vec<ChatMessage>),  // get chat history
     GlobalChatNewMessage(ChatMessage), // one new messages
     LobbyChatSync(Vec<ChatMessage>),   // get lobby history
     LobbyChatNewMessage(ChatMessage),  // one new messages
     GameStarted(usize),                // usize : lobby id
-    GameUpdate(GameUpdate),
-    WinnerAnnouncement(String),
+    GameUpdate(GameUpdate),            // full board, on game start, on resume and on resync
+    GameUpdateDelta(GameUpdateDelta),  // tiles changed since the last update sent
+    PlayerEliminated(PlayerElimination),
+    GameOver(GameOver),
+    GameSeed(u64), // revealed once the game is over, recreates the map and spawns
+    ReplayAvailable(String), // game id, to fetch the replay of the game that just ended
+    RatingsUpdate(Vec<RatingChange>),
+    #[serde(rename = "myMoves")]
     QueuedMoves(PlayerMoves),
+    Spectating(usize), // usize : lobby id
+    SpectatorUpdate(SpectatorUpdate),
+    SpectatingEnded(usize), // usize : lobby id
+    Error(CommandRejection),
 }
 
 impl WsMessageToClient {
+    pub fn to_message(&self, protocol: WsProtocol) -> Message {
+        match protocol {
+            WsProtocol::Text => self.to_string_message(),
+            WsProtocol::Binary => self.to_binary_message(),
+            WsProtocol::Json => self.to_json_message(),
+        }
+    }
+
+    pub fn to_json_message(&self) -> Message {
+        Message::Text(
+            serde_json::to_string(&ServerMessage {
+                version: PROTOCOL_VERSION,
+                message: self,
+            })
+            .expect("failed to jsonize message"),
+        )
+    }
+
+    // Same enum as the text protocol, fields are sent by name so the client doesn't depend on their order
+    pub fn to_binary_message(&self) -> Message {
+        let mut frame = vec![PROTOCOL_VERSION];
+        rmp_serde::encode::write_named(&mut frame, self).expect("failed to encode message");
+        Message::Binary(frame)
+    }
+
     pub fn to_string_message(&self) -> Message {
         match &self {
             WsMessageToClient::Pong => Message::Text("/pong".to_string()),
@@ -66,39 +109,133 @@ impl WsMessageToClient {
                 "/gameUpdate ",
                 serde_json::to_string(game_state).expect("failed to jsonize game_state")
             )),
-            WsMessageToClient::WinnerAnnouncement(winner_name) => {
-                Message::Text(format!("{}{}", "/winnerIs ", winner_name))
+            WsMessageToClient::GameUpdateDelta(delta) => Message::Text(format!(
+                "{}{}",
+                "/gameUpdateDelta ",
+                serde_json::to_string(delta).expect("failed to jsonize game delta")
+            )),
+            WsMessageToClient::PlayerEliminated(elimination) => Message::Text(format!(
+                "{}{}",
+                "/playerEliminated ",
+                serde_json::to_string(elimination).expect("failed to jsonize elimination")
+            )),
+            WsMessageToClient::GameOver(game_over) => Message::Text(format!(
+                "{}{}",
+                "/gameOver ",
+                serde_json::to_string(game_over).expect("failed to jsonize game over")
+            )),
+            WsMessageToClient::GameSeed(seed) => Message::Text(format!("/gameSeed {}", seed)),
+            WsMessageToClient::ReplayAvailable(game_id) => {
+                Message::Text(format!("/replayAvailable {}", game_id))
             }
+            WsMessageToClient::RatingsUpdate(changes) => Message::Text(format!(
+                "{}{}",
+                "/ratingsUpdate ",
+                serde_json::to_string(changes).expect("failed to jsonize rating changes")
+            )),
             WsMessageToClient::QueuedMoves(moves) => Message::Text(format!(
                 "{}{}",
                 "/myMoves ",
                 serde_json::to_string(moves).expect("failed to jsonize game_state")
             )),
+            WsMessageToClient::Error(rejection) => Message::Text(format!(
+                "{}{}",
+                "/error ",
+                serde_json::to_string(rejection).expect("failed to jsonize rejection")
+            )),
+            WsMessageToClient::Spectating(lobby_id) => {
+                Message::Text(format!("/spectating {}", lobby_id))
+            }
+            WsMessageToClient::SpectatorUpdate(update) => Message::Text(format!(
+                "{}{}",
+                "/spectatorUpdate ",
+                serde_json::to_string(update).expect("failed to jsonize spectator update")
+            )),
+            WsMessageToClient::SpectatingEnded(lobby_id) => {
+                Message::Text(format!("/spectatingEnded {}", lobby_id))
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ServerMessage<'a> {
+    pub version: u8,
+    #[serde(flatten)]
+    pub message: &'a WsMessageToClient,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct CommandRejection {
+    pub code: CommandError,
+    pub message: String,
+    pub request_id: Option<String>, // only known with the json protocol
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct LobbiesGeneralUpdate {
     pub lobbies: Vec<LobbyGeneralUpdate>,
     pub connected_players: Vec<(String, Option<usize>)>, // (name,in lobby)
 }
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct LobbyGeneralUpdate {
     pub player_capacity: usize,
-    pub player_names: Vec<String>,
+    pub is_ranked: bool,
+    pub team_size: Option<usize>, // none in free for all
+    pub bots_only: bool,
+    pub map_kind: MapKind,
+    pub custom_map: Option<String>, // name of the map picked by the players, if any
+    pub player_names: Vec<String>,  // bots included
+    pub nb_bots: usize,
+    pub nb_spectators: usize,
     pub status: LobbyStatus,
     pub next_starting_time: i64, // unix timestamp seconds
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct GameUpdate {
     pub board_game: Vec<Vec<TileUpdate>>,
     pub score_board: HashMap<String, PlayerScore>,
     pub moves: PlayerMoves,
+    pub rejected_move: Option<RejectedMove>, // the move played this tick, if it had no effect
     pub tick: usize,
 }
-#[derive(Debug, Clone, Serialize)]
+// Unfogged view of the game, sent to the players who are not part of it
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SpectatorUpdate {
+    pub board_game: Vec<Vec<TileUpdate>>,
+    pub score_board: HashMap<String, PlayerScore>,
+    pub tick: usize,
+}
+// To be applied on top of the board received at base_tick, a client holding another
+// tick missed an update and should ask for a /resync
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct GameUpdateDelta {
+    pub tiles: Vec<TileDelta>,
+    pub score_board: HashMap<String, PlayerScore>,
+    pub moves: PlayerMoves,
+    pub rejected_move: Option<RejectedMove>,
+    pub base_tick: usize,
+    pub tick: usize,
+}
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RejectedMove {
+    pub from: (usize, usize),
+    pub player_move: PlayerMove,
+    pub reason: MoveRejection,
+}
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub enum MoveRejection {
+    IntoWall,
+    BlockedByMountain,
+    NotEnoughTroops,
+    TileNotOwned, // the tile was lost before the move was played
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct TileDelta(pub usize, pub usize, pub TileUpdate); // x, y, new tile
+
+#[derive(Debug, Clone, Serialize, PartialEq, JsonSchema)]
 pub struct TileUpdate {
     pub status: TileStatus,
     pub tile_type: TileType,
@@ -108,26 +245,59 @@ pub struct TileUpdate {
 }
 
 impl TileUpdate {
-    pub fn from_game_tile(&mut self, tile: &Tile, lobby_players: &HashMap<String, String>) {
+    pub fn reveal(&mut self, tile: &Tile, player_name: Option<String>) {
         self.status = tile.status.clone();
         self.tile_type = tile.tile_type.clone();
-        self.player_name = None;
+        self.player_name = player_name;
         self.nb_troops = tile.nb_troops;
         self.hidden = false;
-        if let Some(player_uuid) = tile.player_uuid.clone() {
-            self.player_name = Some(
-                lobby_players
-                    .get(&player_uuid)
-                    .expect("couldn't find player")
-                    .into(),
-            );
-        }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct PlayerScore {
     pub total_troops: usize,
     pub total_positions: usize,
     pub color: Color,
+    pub team: Option<usize>, // none in free for all
+    pub is_bot: bool,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RatingChange {
+    pub player_name: String,
+    pub rating_before: i64,
+    pub rating_after: i64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PlayerElimination {
+    pub name: String,
+    pub by: Option<String>, // name of the player who took the kingdom, none on surrender
+    pub tick: usize,
+    pub placement: usize,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct GameOver {
+    pub reason: GameOverReason,
+    pub winner: Option<String>,
+    pub winning_team: Option<usize>,
+    pub ranking: Vec<RankedPlayer>, // ordered by placement, tied players share a placement
+}
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub enum GameOverReason {
+    LastPlayerStanding,
+    LastTeamStanding,
+    NoSurvivor,
+    NobodyActive, // players still occupying some tiles, but nobody is connected
+}
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RankedPlayer {
+    pub name: String,
+    pub placement: usize,
+    pub total_positions: usize,
+    pub total_troops: usize,
+    pub team: Option<usize>,
+    pub is_bot: bool,
 }
@@ -1,27 +1,224 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::{
+    commons::WsProtocol,
+    messages_to_clients::{PlayerScore, TileUpdate},
+};
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct WsConnectionRequest {
+    #[serde(default)]
+    pub protocol: WsProtocol,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CreatePlayerRequest {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct RequestNewPlayerResponse {
     pub uuid: String,
     pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+pub struct CreateBotAccountRequest {
+    pub name: String,
+    pub api_key_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct NewBotAccountRequest {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct NewBotAccountResponse {
+    pub uuid: String,
+    pub name: String,
+    pub api_key: String, // only given once, to be kept by the bot author
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct UpdateNameRequest {
     pub name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct IsValidPlayernameRequest {
     pub name: String,
 }
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 pub struct IsValidPlayernameResponse {
     pub is_valid: bool,
     pub reason: Option<String>,
 }
+
+#[derive(Serialize, Debug, JsonSchema)]
+pub struct ReplayTickResponse {
+    pub tick: usize,
+    pub board_game: Vec<Vec<TileUpdate>>,
+    pub score_board: HashMap<String, PlayerScore>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateGameRequest {
+    pub game_id: String,
+    pub lobby_id: usize,
+    pub player_capacity: usize,
+    pub mode: GameMode,
+    pub started_at: i64, // unix timestamp seconds
+    pub ended_at: i64,   // unix timestamp seconds
+    pub nb_ticks: usize,
+    pub winner_name: Option<String>,
+    pub winning_team: Option<usize>,
+    pub players: Vec<CreateGamePlayerRequest>,
+}
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateGamePlayerRequest {
+    pub player_uuid: String,
+    pub player_name: String,
+    pub placement: usize,
+    pub total_positions: usize,
+    pub total_troops: usize,
+    pub team: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct PaginationRequest {
+    pub page: Option<usize>,      // 0 indexed
+    pub page_size: Option<usize>, // defaults to DEFAULT_PAGE_SIZE, at most MAX_PAGE_SIZE
+}
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct PaginatedResponse<T> {
+    pub items: Vec<T>,
+    pub page: usize,
+    pub page_size: usize,
+    pub total_items: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct GameResponse {
+    pub game_id: String,
+    pub lobby_id: usize,
+    pub player_capacity: usize,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub nb_ticks: usize,
+    pub winner_name: Option<String>,
+    pub winning_team: Option<usize>,
+    pub players: Vec<GamePlayerResponse>, // ordered by placement
+}
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct GamePlayerResponse {
+    pub player_name: String,
+    pub placement: usize,
+    pub total_positions: usize,
+    pub total_troops: usize,
+    pub team: Option<usize>,
+}
+
+// One game seen from one player
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct PlayerGameResponse {
+    pub game_id: String,
+    pub lobby_id: usize,
+    pub player_capacity: usize,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub nb_ticks: usize,
+    pub winner_name: Option<String>,
+    pub winning_team: Option<usize>,
+    pub placement: usize,
+    pub total_positions: usize,
+    pub total_troops: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CreateMapRequest {
+    pub map_id: String,
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub nb_spawns: usize,
+    pub created_at: i64, // unix timestamp seconds
+    pub data: Vec<u8>,
+}
+// The map itself is fetched from /maps/:map_id
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct MapResponse {
+    pub map_id: String,
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub nb_spawns: usize, // the lobbies with more seats can't use it
+    pub created_at: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SaveRatingRequest {
+    pub player_uuid: String,
+    pub rating_before: i64,
+    pub rating_after: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardSort {
+    #[default]
+    Rating,
+    Wins,
+    WinRate,
+    GamesPlayed,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardPeriod {
+    #[default]
+    AllTime,
+    Monthly, // since the first day of the current month, UTC
+    Weekly,  // since the last monday, UTC
+}
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum GameMode {
+    FreeForAll,
+    Teams,
+    Bots, // lobbies reserved to bot accounts
+}
+impl GameMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GameMode::FreeForAll => "free_for_all",
+            GameMode::Teams => "teams",
+            GameMode::Bots => "bots",
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct LeaderboardRequest {
+    pub sort_by: Option<LeaderboardSort>,
+    pub period: Option<LeaderboardPeriod>,
+    pub mode: Option<GameMode>,         // all modes when missing
+    pub player_capacity: Option<usize>, // all lobbies when missing
+    pub limit: Option<usize>,           // at most LEADERBOARD_SIZE
+}
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub player_name: String,
+    pub rating: i64,
+    pub games_played: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+}
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct LeaderboardResponse {
+    pub refreshed_at: i64, // unix timestamp seconds
+    pub entries: Vec<LeaderboardEntry>,
+}
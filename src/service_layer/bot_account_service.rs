@@ -0,0 +1,54 @@
+use crate::configs::app_state::AppState;
+use crate::constants::BOT_API_KEY_LENGTH;
+use crate::custom_errors::service_errors::ServiceError;
+use crate::data_access_layer::bot_account_dal;
+use crate::requests::requests::{
+    CreateBotAccountRequest, NewBotAccountRequest, NewBotAccountResponse,
+};
+use crate::utilities::responses::{response_ok, ApiResponse};
+use axum::{extract::State, http::StatusCode, Json};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use super::player_service::internal_is_valid_playername;
+
+// Bot accounts are players connecting through /bots/ws with their api key in the
+// BOT_API_KEY_HEADER header, they can only join the lobbies reserved to bots
+pub async fn create_bot_account(
+    State(state): State<Arc<AppState>>,
+    Json(new_bot_account_request): Json<NewBotAccountRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<NewBotAccountResponse>>), ServiceError> {
+    let is_valid = internal_is_valid_playername(new_bot_account_request.name.clone(), &state)?;
+    if !is_valid.is_valid {
+        return Err(ServiceError::InvalidPlayername(
+            is_valid.reason.unwrap_or_default(),
+        ));
+    };
+    let api_key: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(BOT_API_KEY_LENGTH)
+        .map(char::from)
+        .collect();
+    let uuid = bot_account_dal::create_bot_account(
+        &state,
+        CreateBotAccountRequest {
+            name: new_bot_account_request.name.clone(),
+            api_key_hash: hash_api_key(&api_key),
+        },
+    )?;
+
+    response_ok(Some(NewBotAccountResponse {
+        uuid,
+        name: new_bot_account_request.name,
+        api_key,
+    }))
+}
+
+// Only the hash is stored, a leaked database doesn't give away the keys
+pub fn hash_api_key(api_key: &str) -> String {
+    Sha256::digest(api_key.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,353 @@
+use crate::{
+    configs::app_state::{Lobby, LobbyStatus, TileStatus, TileType},
+    constants::{BOT_PATH_LENGTH, DELAY_FOR_GAMESTART_SEC, EASY_BOT_TICK_INTERVAL},
+    models::messages_to_clients::TileUpdate,
+};
+use chrono::Utc;
+use rand::{seq::SliceRandom, Rng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::{
+    game_engine::GameEngine,
+    player_service::{Direction, PlayerMove},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum BotDifficulty {
+    Easy,   // random moves, a few times slower than a player
+    Medium, // grabs whatever it can take, then moves its biggest army towards the closest free tile
+    Hard,   // same, but goes after the enemies first and rushes the kingdoms it sees
+}
+
+impl std::str::FromStr for BotDifficulty {
+    type Err = ();
+    fn from_str(input: &str) -> Result<BotDifficulty, Self::Err> {
+        match input {
+            "easy" => Ok(BotDifficulty::Easy),
+            "medium" => Ok(BotDifficulty::Medium),
+            "hard" => Ok(BotDifficulty::Hard),
+            _ => Err(()),
+        }
+    }
+}
+
+// Names given to the server-side bots, "<difficulty> bot <number>", reserved so that no player takes one
+pub fn is_bot_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    match name.split_once(" bot ") {
+        Some((difficulty, number)) => {
+            difficulty.parse::<BotDifficulty>().is_ok()
+                && !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+// The caller checks that the lobby is still awaiting players and not full
+pub fn add_bot(lobby: &mut Lobby, difficulty: BotDifficulty) {
+    let bot_uuid = format!("bot-{}", Uuid::now_v7());
+    let bot_name = (1..)
+        .map(|i| format!("{:?} bot {}", difficulty, i))
+        .find(|name| {
+            !lobby
+                .players
+                .values()
+                .any(|player_name| player_name == name)
+        })
+        .expect("no bot name available");
+    lobby.players.insert(bot_uuid.clone(), bot_name);
+    lobby.bots.insert(bot_uuid, difficulty);
+    if lobby.players.len() == lobby.player_capacity {
+        println!("lobby {} is starting", lobby.lobby_id);
+        lobby.status = LobbyStatus::StartingSoon;
+        lobby.next_starting_time = Utc::now().timestamp() + DELAY_FOR_GAMESTART_SEC;
+    }
+}
+
+// Completes a lobby with bots once its first player waited long enough, returns true if bots were added.
+// The lobby of the bot accounts is left to them.
+pub fn fill_with_bots(lobby: &mut Lobby) -> bool {
+    if lobby.bots_only {
+        return false;
+    }
+    let Some(fill_delay) = lobby.bot_fill_delay_sec else {
+        return false;
+    };
+    if lobby.players.is_empty()
+        || lobby.players.len() >= lobby.player_capacity
+        || Utc::now().timestamp() - lobby.awaiting_since < fill_delay
+    {
+        return false;
+    }
+    while lobby.players.len() < lobby.player_capacity {
+        add_bot(lobby, BotDifficulty::Medium);
+    }
+    true
+}
+
+// Bots play through the same queue as the players, and only know what their fogged view shows
+pub fn queue_bot_moves(
+    engine: &mut GameEngine,
+    bots: &HashMap<String, BotDifficulty>,
+    rng: &mut impl Rng,
+) {
+    // sorted, so that a seeded rng always gives the same moves
+    let mut bot_uuids: Vec<&String> = bots.keys().collect();
+    bot_uuids.sort();
+    for bot_uuid in bot_uuids {
+        let difficulty = &bots[bot_uuid];
+        let is_idle = engine
+            .player_moves(bot_uuid)
+            .is_some_and(|moves| moves.queued_moves.is_empty());
+        if !is_idle || engine.is_eliminated(bot_uuid) {
+            continue;
+        }
+        if *difficulty == BotDifficulty::Easy && !engine.tick.is_multiple_of(EASY_BOT_TICK_INTERVAL)
+        {
+            continue;
+        }
+        let view = engine.fog_of_war_view(bot_uuid);
+        let holders = holders(engine, &view, bot_uuid);
+        let armies = armies(&view, &holders);
+
+        if *difficulty == BotDifficulty::Easy {
+            if let Some(new_move) = random_move(engine, &view, &holders, &armies, rng) {
+                engine.queue_move(bot_uuid, new_move);
+            }
+            continue;
+        }
+        if let Some(new_move) = best_capture(engine, &view, &holders, &armies, *difficulty) {
+            engine.queue_move(bot_uuid, new_move);
+            continue;
+        }
+        let Some(&(army_xy, nb_troops)) = armies.first() else {
+            continue;
+        };
+        let target = match *difficulty {
+            BotDifficulty::Hard => visible_kingdom(&view, &holders, nb_troops)
+                .or_else(|| closest_free_tile(&view, &holders, army_xy, nb_troops)),
+            _ => closest_free_tile(&view, &holders, army_xy, nb_troops),
+        };
+        if let Some(target) = target {
+            engine.select(bot_uuid, army_xy);
+            if let Some(path) = engine.path_to(bot_uuid, target) {
+                engine.queue_path(bot_uuid, &path[..path.len().min(BOT_PATH_LENGTH)]);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Holder {
+    Nobody, // empty or out of sight
+    Bot,
+    Ally,
+    Enemy,
+}
+
+// Who holds each tile the bot sees. Players are told apart by uuid, not by the names shown
+// in the view : a player could go by the name of the bot or of one of its allies.
+fn holders(engine: &GameEngine, view: &[Vec<TileUpdate>], bot_uuid: &str) -> Vec<Vec<Holder>> {
+    let allies = engine.allies_of(bot_uuid);
+    view.iter()
+        .zip(engine.board.iter())
+        .map(|(view_column, column)| {
+            view_column
+                .iter()
+                .zip(column.iter())
+                .map(|(seen, tile)| match &tile.player_uuid {
+                    _ if seen.hidden => Holder::Nobody,
+                    None => Holder::Nobody,
+                    Some(uuid) if uuid == bot_uuid => Holder::Bot,
+                    Some(uuid) if allies.contains(uuid) => Holder::Ally,
+                    Some(_) => Holder::Enemy,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Tiles of the bot able to send troops, biggest army first
+fn armies(view: &[Vec<TileUpdate>], holders: &[Vec<Holder>]) -> Vec<((usize, usize), usize)> {
+    let mut armies = vec![];
+    for (x, column) in view.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            if holders[x][y] == Holder::Bot && tile.nb_troops > 1 {
+                armies.push(((x, y), tile.nb_troops));
+            }
+        }
+    }
+    armies.sort_by_key(|(_, nb_troops)| std::cmp::Reverse(*nb_troops));
+    armies
+}
+
+// Neighbouring tiles a move can end on, walls excluded
+fn neighbours(
+    xy: (usize, usize),
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (Direction, (usize, usize))> {
+    [
+        Direction::Left,
+        Direction::Right,
+        Direction::Up,
+        Direction::Down,
+    ]
+    .into_iter()
+    .map(move |direction| (direction, direction.destination(xy, width, height)))
+    .filter(move |(_, destination)| *destination != xy)
+}
+
+fn random_move(
+    engine: &GameEngine,
+    view: &[Vec<TileUpdate>],
+    holders: &[Vec<Holder>],
+    armies: &[((usize, usize), usize)],
+    rng: &mut impl Rng,
+) -> Option<PlayerMove> {
+    let (from, _) = armies.choose(rng)?;
+    let destinations: Vec<Direction> = neighbours(*from, engine.width(), engine.height())
+        .filter(|(_, (x, y))| {
+            view[*x][*y].tile_type != TileType::Mountain
+                && matches!(holders[*x][*y], Holder::Nobody | Holder::Enemy)
+        })
+        .map(|(direction, _)| direction)
+        .collect();
+    Some(PlayerMove {
+        direction: *destinations.choose(rng)?,
+        half: false,
+        from: Some(*from),
+    })
+}
+
+// The most valuable tile the bot can take right now, if any
+fn best_capture(
+    engine: &GameEngine,
+    view: &[Vec<TileUpdate>],
+    holders: &[Vec<Holder>],
+    armies: &[((usize, usize), usize)],
+    difficulty: BotDifficulty,
+) -> Option<PlayerMove> {
+    let mut best: Option<(usize, PlayerMove)> = None;
+    for (from, nb_troops) in armies.iter() {
+        for (direction, (x, y)) in neighbours(*from, engine.width(), engine.height()) {
+            let tile = &view[x][y];
+            let nb_attacking_troops = nb_troops - 1;
+            if tile.tile_type == TileType::Mountain
+                || matches!(holders[x][y], Holder::Bot | Holder::Ally)
+                || nb_attacking_troops <= tile.nb_troops
+            {
+                continue;
+            }
+            let value = match (&tile.tile_type, &tile.status, difficulty) {
+                (TileType::Kingdom, _, _) => 1000,
+                (_, TileStatus::Occupied, BotDifficulty::Hard) => 100,
+                (TileType::Castle, _, BotDifficulty::Hard) => 50,
+                (TileType::Castle, _, _) => 5, // costly for a medium bot, taken last
+                _ => 10,
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(best_value, _)| value > *best_value)
+            {
+                best = Some((
+                    value,
+                    PlayerMove {
+                        direction,
+                        half: false,
+                        from: Some(*from),
+                    },
+                ));
+            }
+        }
+    }
+    best.map(|(_, best_move)| best_move)
+}
+
+// Closest tile by manhattan distance the army could take, the path itself goes around the mountains
+fn closest_free_tile(
+    view: &[Vec<TileUpdate>],
+    holders: &[Vec<Holder>],
+    from: (usize, usize),
+    nb_troops: usize,
+) -> Option<(usize, usize)> {
+    let mut closest: Option<(usize, (usize, usize))> = None;
+    for (x, column) in view.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            let is_free = matches!(holders[x][y], Holder::Nobody | Holder::Enemy);
+            if tile.tile_type == TileType::Mountain || !is_free || tile.nb_troops + 1 >= nb_troops {
+                continue;
+            }
+            let distance = x.abs_diff(from.0) + y.abs_diff(from.1);
+            if closest.is_none_or(|(closest_distance, _)| distance < closest_distance) {
+                closest = Some((distance, (x, y)));
+            }
+        }
+    }
+    closest.map(|(_, xy)| xy)
+}
+
+fn visible_kingdom(
+    view: &[Vec<TileUpdate>],
+    holders: &[Vec<Holder>],
+    nb_troops: usize,
+) -> Option<(usize, usize)> {
+    for (x, column) in view.iter().enumerate() {
+        for (y, tile) in column.iter().enumerate() {
+            if tile.tile_type == TileType::Kingdom
+                && holders[x][y] == Holder::Enemy
+                && tile.nb_troops + 1 < nb_troops
+            {
+                return Some((x, y));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{configs::app_state::Tile, service_layer::player_service::Color};
+
+    #[test]
+    fn bot_names_are_reserved() {
+        assert!(is_bot_name("Medium bot 1"));
+        assert!(is_bot_name("hard bot 12"));
+        assert!(!is_bot_name("Medium bot"));
+        assert!(!is_bot_name("Medium bot 1a"));
+        assert!(!is_bot_name("Clever bot 1"));
+    }
+
+    #[test]
+    fn bots_tell_players_apart_by_uuid() {
+        let mut engine = GameEngine::new(vec![vec![Tile::default(); 1]; 10]);
+        for (uuid, xy) in [("bot-1", (0, 0)), ("impostor", (9, 0))] {
+            let name = "Medium bot 1".to_string();
+            engine.add_player(
+                uuid.to_string(),
+                name,
+                Color::Red,
+                xy,
+                None,
+                uuid == "bot-1",
+            );
+        }
+        engine.board[0][0].nb_troops = 4;
+        engine.board[1][0] = Tile {
+            status: TileStatus::Occupied,
+            tile_type: TileType::Blank,
+            player_uuid: Some("impostor".to_string()),
+            nb_troops: 2,
+        };
+        let view = engine.fog_of_war_view("bot-1");
+        let holders = holders(&engine, &view, "bot-1");
+        assert_eq!(holders[1][0], Holder::Enemy);
+        assert_eq!(holders[9][0], Holder::Nobody); // out of sight
+        assert_eq!(armies(&view, &holders), vec![((0, 0), 4)]);
+    }
+}
@@ -0,0 +1,1277 @@
+use crate::{
+    configs::app_state::{Tile, TileStatus, TileType},
+    constants::{MAX_QUEUED_MOVES, MAX_QUEUED_PATH_MOVES, TICK_BLANK, TICK_CASTLE, TICK_KINGDOM},
+    models::messages_to_clients::{MoveRejection, PlayerScore, TileUpdate},
+};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+};
+
+use super::player_service::{Color, Direction, PlayerMove, PlayerMoves};
+
+// Pure game rules : no locks, no channels, no clock. The game loop feeds it the
+// players still connected each tick and fans out what comes back.
+#[derive(Debug, Clone)]
+pub struct GameEngine {
+    pub board: Vec<Vec<Tile>>,
+    pub players: BTreeMap<String, EnginePlayer>, // uuid->player, ordered so moves resolve in the same order every time
+    pub tick: usize,
+    pub pending_surrenders: Vec<String>, // applied at the start of the next tick
+}
+
+#[derive(Debug, Clone)]
+pub struct EnginePlayer {
+    pub name: String,
+    pub color: Color,
+    pub xy: (usize, usize),
+    pub queued_moves: VecDeque<PlayerMove>,
+    pub placement: Option<usize>, // known once eliminated
+    pub team: Option<usize>,      // none in free for all
+    pub is_bot: bool,
+    pub in_transit: Option<((usize, usize), usize)>, // troops the last move left on an ally's tile
+}
+
+#[derive(Debug, Default)]
+pub struct TickInputs {
+    pub active_players: HashSet<String>, // uuids of the players still connected to this game
+}
+
+#[derive(Debug)]
+pub struct TickOutcome {
+    pub tick: usize,
+    pub played_moves: Vec<PlayedMove>,
+    pub eliminations: Vec<Elimination>,
+    pub score_board: HashMap<String, PlayerScore>,
+    pub status: GameStatus,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayedMove {
+    pub player_uuid: String,
+    pub from: (usize, usize),
+    pub player_move: PlayerMove,
+    pub outcome: OutcomeAssault,
+}
+
+#[derive(Debug, Clone)]
+pub struct Elimination {
+    pub player_uuid: String,
+    pub by: Option<String>, // uuid of the player who took the kingdom, none on surrender
+    pub tick: usize,
+    pub placement: usize, // players eliminated during the same tick share the placement
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerRanking {
+    pub player_uuid: String,
+    pub name: String,
+    pub placement: usize, // 1 is the winner, tied players share the same placement
+    pub total_positions: usize,
+    pub total_troops: usize,
+    pub team: Option<usize>,
+    pub is_bot: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameStatus {
+    Running,
+    Won(String),    // winner name
+    TeamWon(usize), // winning team, every survivor belongs to it
+    NoSurvivor,
+    NobodyActive, // players still occupying some tiles, but nobody is connected
+}
+
+#[derive(Debug, Clone)]
+pub enum OutcomeAssault {
+    AttackingSameTile, // happens typically on side of the board, when going into a wall
+    BlockedByMountain,
+    NotEnoughTroops,
+    TileNotOwned,
+    SelfTroopsMove, // into an own or allied tile, the troops join it and the tile keeps its owner
+    ConquerEmpty,
+    Tie,
+    Victory(usize),                 // how many invaders survive on defensive case
+    CapturedKingdom(String, usize), // loser_uuid, how many invaders survive, the loser is eliminated
+    Defeat(usize),                  // how many loss on defensive case (the moving troops are lost)
+    VictoryCastle(usize),
+}
+
+impl OutcomeAssault {
+    // Outcomes of a move that left the board untouched
+    pub fn rejection(&self) -> Option<MoveRejection> {
+        match self {
+            OutcomeAssault::AttackingSameTile => Some(MoveRejection::IntoWall),
+            OutcomeAssault::BlockedByMountain => Some(MoveRejection::BlockedByMountain),
+            OutcomeAssault::NotEnoughTroops => Some(MoveRejection::NotEnoughTroops),
+            OutcomeAssault::TileNotOwned => Some(MoveRejection::TileNotOwned),
+            _ => None,
+        }
+    }
+}
+
+impl GameEngine {
+    pub fn new(board: Vec<Vec<Tile>>) -> Self {
+        GameEngine {
+            board,
+            players: BTreeMap::new(),
+            tick: 0,
+            pending_surrenders: vec![],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.board.len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.board[0].len()
+    }
+
+    pub fn add_player(
+        &mut self,
+        uuid: String,
+        name: String,
+        color: Color,
+        xy: (usize, usize),
+        team: Option<usize>,
+        is_bot: bool,
+    ) {
+        self.board[xy.0][xy.1] = Tile {
+            status: TileStatus::Occupied,
+            tile_type: TileType::Kingdom,
+            nb_troops: 1,
+            player_uuid: Some(uuid.clone()),
+        };
+        self.players.insert(
+            uuid,
+            EnginePlayer {
+                name,
+                color,
+                xy,
+                queued_moves: VecDeque::new(),
+                placement: None,
+                team,
+                is_bot,
+                in_transit: None,
+            },
+        );
+    }
+
+    pub fn is_on_board(&self, xy: (usize, usize)) -> bool {
+        xy.0 < self.width() && xy.1 < self.height()
+    }
+
+    // The player and their teammates, in free for all a player is their own only ally
+    pub fn allies_of(&self, player_uuid: &str) -> HashSet<String> {
+        let team = self.players.get(player_uuid).and_then(|player| player.team);
+        self.players
+            .iter()
+            .filter(|(uuid, player)| {
+                uuid.as_str() == player_uuid || (team.is_some() && player.team == team)
+            })
+            .map(|(uuid, _)| uuid.clone())
+            .collect()
+    }
+
+    // Returns false when the move was dropped : unknown player, queue already full or origin out of the board
+    pub fn queue_move(&mut self, player_uuid: &str, new_move: PlayerMove) -> bool {
+        if new_move.from.is_some_and(|from| !self.is_on_board(from)) {
+            return false;
+        }
+        match self.players.get_mut(player_uuid) {
+            Some(player) if player.queued_moves.len() < MAX_QUEUED_MOVES => {
+                player.queued_moves.push_back(new_move);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn clear_moves(&mut self, player_uuid: &str) {
+        if let Some(player) = self.players.get_mut(player_uuid) {
+            player.queued_moves.clear();
+        }
+    }
+
+    // Removes the last queued move, returns false when the queue is empty
+    pub fn undo_move(&mut self, player_uuid: &str) -> bool {
+        self.players
+            .get_mut(player_uuid)
+            .is_some_and(|player| player.queued_moves.pop_back().is_some())
+    }
+
+    // Removes the queued moves from `index` onwards, returns false when there is no move at `index`
+    pub fn cancel_moves_from(&mut self, player_uuid: &str, index: usize) -> bool {
+        match self.players.get_mut(player_uuid) {
+            Some(player) if index < player.queued_moves.len() => {
+                player.queued_moves.truncate(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Returns false when the path doesn't fit in the queue
+    pub fn queue_path(&mut self, player_uuid: &str, path: &[Direction]) -> bool {
+        match self.players.get_mut(player_uuid) {
+            Some(player) if player.queued_moves.len() + path.len() <= MAX_QUEUED_PATH_MOVES => {
+                player
+                    .queued_moves
+                    .extend(path.iter().map(|direction| PlayerMove {
+                        direction: *direction,
+                        half: false,
+                        from: None,
+                    }));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Shortest path to `target` from where the queued moves will leave the cursor, going
+    // around the mountains the player can see (hidden castles and kingdoms look like mountains)
+    pub fn path_to(&self, player_uuid: &str, target: (usize, usize)) -> Option<Vec<Direction>> {
+        let player = self.players.get(player_uuid)?;
+        if !self.is_on_board(target) {
+            return None;
+        }
+        let (width, height) = (self.width(), self.height());
+        let view = self.fog_of_war_view(player_uuid);
+        let start = player
+            .queued_moves
+            .iter()
+            .fold(player.xy, |xy, queued| match queued.from {
+                Some(_) => xy,
+                None => queued.destination(xy, width, height),
+            });
+
+        let mut previous: HashMap<(usize, usize), ((usize, usize), Direction)> = HashMap::new();
+        let mut visited = HashSet::from([start]);
+        let mut to_visit = VecDeque::from([start]);
+        while let Some(xy) = to_visit.pop_front() {
+            if xy == target {
+                break;
+            }
+            for direction in [
+                Direction::Left,
+                Direction::Right,
+                Direction::Up,
+                Direction::Down,
+            ] {
+                let next = direction.destination(xy, width, height);
+                if view[next.0][next.1].tile_type == TileType::Mountain || !visited.insert(next) {
+                    continue;
+                }
+                previous.insert(next, (xy, direction));
+                to_visit.push_back(next);
+            }
+        }
+        if !visited.contains(&target) {
+            return None;
+        }
+
+        let mut path = vec![];
+        let mut xy = target;
+        while xy != start {
+            let (previous_xy, direction) = previous[&xy];
+            path.push(direction);
+            xy = previous_xy;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    // Moves the cursor to one of the player's tiles, returns false for any other tile
+    pub fn select(&mut self, player_uuid: &str, xy: (usize, usize)) -> bool {
+        if !self.is_on_board(xy)
+            || self.board[xy.0][xy.1].player_uuid.as_deref() != Some(player_uuid)
+        {
+            return false;
+        }
+        match self.players.get_mut(player_uuid) {
+            Some(player) if player.placement.is_none() => {
+                player.xy = xy;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Returns false when the player is unknown, already eliminated or already surrendering
+    pub fn surrender(&mut self, player_uuid: &str) -> bool {
+        match self.players.get(player_uuid) {
+            Some(player)
+                if player.placement.is_none()
+                    && !self
+                        .pending_surrenders
+                        .iter()
+                        .any(|uuid| uuid == player_uuid) =>
+            {
+                self.pending_surrenders.push(player_uuid.to_string());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn is_eliminated(&self, player_uuid: &str) -> bool {
+        self.players
+            .get(player_uuid)
+            .is_some_and(|player| player.placement.is_some())
+    }
+
+    pub fn player_moves(&self, player_uuid: &str) -> Option<PlayerMoves> {
+        self.players.get(player_uuid).map(|player| PlayerMoves {
+            queued_moves: player.queued_moves.clone(),
+            xy: player.xy,
+        })
+    }
+
+    pub fn step(&mut self, inputs: &TickInputs) -> TickOutcome {
+        self.tick += 1;
+        let mut eliminations = self.apply_surrenders();
+        self.grow_troops();
+
+        let mut played_moves = vec![];
+        let player_uuids: Vec<String> = self.players.keys().cloned().collect();
+        for player_uuid in player_uuids.iter() {
+            if inputs.active_players.contains(player_uuid) && !self.is_eliminated(player_uuid) {
+                if let Some(played_move) = self.play_next_move(player_uuid) {
+                    if let OutcomeAssault::CapturedKingdom(loser_uuid, _) = &played_move.outcome {
+                        eliminations.push(self.eliminate(loser_uuid, Some(player_uuid.clone())));
+                    }
+                    played_moves.push(played_move);
+                }
+            }
+        }
+
+        // everyone eliminated during this tick shares the best of the placements they leave
+        let placement = self.nb_alive_players() + 1;
+        for elimination in eliminations.iter_mut() {
+            elimination.placement = placement;
+            if let Some(player) = self.players.get_mut(&elimination.player_uuid) {
+                player.placement = Some(placement);
+            }
+        }
+
+        let score_board = self.score_board(&inputs.active_players);
+        let remaining_players: Vec<&EnginePlayer> = self
+            .players
+            .values()
+            .filter(|player| player.placement.is_none())
+            .collect();
+        let mut remaining_teams: Vec<Option<usize>> =
+            remaining_players.iter().map(|player| player.team).collect();
+        remaining_teams.sort();
+        remaining_teams.dedup();
+        // bots alone don't keep a game running
+        let nb_active = score_board
+            .values()
+            .filter(|score| score.color != Color::Grey && !score.is_bot)
+            .count();
+        let status = match (remaining_players.len(), remaining_teams.as_slice()) {
+            (0, _) => GameStatus::NoSurvivor,
+            (_, [Some(team)]) => GameStatus::TeamWon(*team),
+            (1, _) => GameStatus::Won(remaining_players[0].name.clone()),
+            _ if nb_active == 0 => GameStatus::NobodyActive,
+            _ => GameStatus::Running,
+        };
+
+        TickOutcome {
+            tick: self.tick,
+            played_moves,
+            eliminations,
+            score_board,
+            status,
+        }
+    }
+
+    // Inactive and eliminated players are shown in grey
+    pub fn score_board(&self, active_players: &HashSet<String>) -> HashMap<String, PlayerScore> {
+        let mut score_board: HashMap<String, PlayerScore> = self
+            .players
+            .iter()
+            .map(|(player_uuid, player)| {
+                let color = if active_players.contains(player_uuid) && player.placement.is_none() {
+                    player.color.clone()
+                } else {
+                    Color::Grey
+                };
+                (
+                    player.name.clone(),
+                    PlayerScore {
+                        total_positions: 0,
+                        total_troops: 0,
+                        color,
+                        team: player.team,
+                        is_bot: player.is_bot,
+                    },
+                )
+            })
+            .collect();
+        for position in self.board.iter().flatten() {
+            if let Some(player_name) = self.occupier_name(position) {
+                score_board.entry(player_name).and_modify(|score| {
+                    score.total_positions += 1;
+                    score.total_troops += position.nb_troops;
+                });
+            }
+        }
+        score_board
+    }
+
+    // Players still in game ranked by tiles then troops held, followed by the
+    // eliminated ones in their elimination order
+    pub fn ranking(&self) -> Vec<PlayerRanking> {
+        let mut ranking: Vec<PlayerRanking> = self
+            .players
+            .iter()
+            .map(|(player_uuid, player)| PlayerRanking {
+                player_uuid: player_uuid.clone(),
+                name: player.name.clone(),
+                placement: player.placement.unwrap_or(0),
+                total_positions: 0,
+                total_troops: 0,
+                team: player.team,
+                is_bot: player.is_bot,
+            })
+            .collect();
+        for position in self.board.iter().flatten() {
+            if let Some(occupier) = ranking
+                .iter_mut()
+                .find(|player| position.player_uuid.as_ref() == Some(&player.player_uuid))
+            {
+                occupier.total_positions += 1;
+                occupier.total_troops += position.nb_troops;
+            }
+        }
+        let (alive, mut eliminated): (Vec<PlayerRanking>, Vec<PlayerRanking>) = ranking
+            .into_iter()
+            .partition(|player| player.placement == 0);
+        // surviving teammates share their placement, ranked on the tiles then troops of their team
+        let standings: Vec<(usize, usize)> = alive
+            .iter()
+            .map(|player| {
+                alive
+                    .iter()
+                    .filter(|other| {
+                        other.player_uuid == player.player_uuid
+                            || (player.team.is_some() && other.team == player.team)
+                    })
+                    .fold((0, 0), |(positions, troops), other| {
+                        (
+                            positions + other.total_positions,
+                            troops + other.total_troops,
+                        )
+                    })
+            })
+            .collect();
+        let mut alive: Vec<((usize, usize), PlayerRanking)> =
+            standings.into_iter().zip(alive).collect();
+        alive.sort_by(|(a_standing, a), (b_standing, b)| {
+            (b_standing, b.total_positions, b.total_troops).cmp(&(
+                a_standing,
+                a.total_positions,
+                a.total_troops,
+            ))
+        });
+        for i in 0..alive.len() {
+            alive[i].1.placement = match i {
+                0 => 1,
+                _ if alive[i].0 == alive[i - 1].0 => alive[i - 1].1.placement,
+                _ => i + 1,
+            };
+        }
+        let mut ranking: Vec<PlayerRanking> = alive.into_iter().map(|(_, player)| player).collect();
+        eliminated.sort_by_key(|player| player.placement);
+        ranking.append(&mut eliminated);
+        ranking
+    }
+
+    fn nb_alive_players(&self) -> usize {
+        self.players
+            .values()
+            .filter(|player| player.placement.is_none())
+            .count()
+    }
+
+    fn apply_surrenders(&mut self) -> Vec<Elimination> {
+        let surrenders: Vec<String> = self.pending_surrenders.drain(..).collect();
+        surrenders
+            .into_iter()
+            .map(|player_uuid| {
+                self.release_territory(&player_uuid);
+                self.eliminate(&player_uuid, None)
+            })
+            .collect()
+    }
+
+    // The placement is settled once the tick is over, when the number of survivors is known
+    fn eliminate(&mut self, player_uuid: &str, by: Option<String>) -> Elimination {
+        let player = self
+            .players
+            .get_mut(player_uuid)
+            .expect("elimination of an unknown player");
+        player.placement = Some(0);
+        player.queued_moves.clear();
+        Elimination {
+            player_uuid: player_uuid.to_string(),
+            by,
+            tick: self.tick,
+            placement: 0,
+        }
+    }
+
+    // The tiles of a player who surrendered turn neutral : his kingdom becomes a castle
+    // and castles keep their garrison, the other tiles are left empty
+    fn release_territory(&mut self, player_uuid: &str) {
+        for position in self.board.iter_mut().flatten() {
+            if position.player_uuid.as_deref() != Some(player_uuid) {
+                continue;
+            }
+            match position.tile_type {
+                TileType::Kingdom | TileType::Castle => {
+                    position.tile_type = TileType::Castle;
+                    position.status = TileStatus::Empty;
+                    position.player_uuid = None;
+                }
+                _ => {
+                    *position = Tile {
+                        tile_type: position.tile_type.clone(),
+                        ..Tile::default()
+                    }
+                }
+            }
+        }
+    }
+
+    fn grow_troops(&mut self) {
+        for position in self.board.iter_mut().flatten() {
+            match position.status {
+                TileStatus::Occupied => match position.tile_type {
+                    TileType::Kingdom if self.tick.is_multiple_of(TICK_KINGDOM) => {
+                        position.nb_troops += 1
+                    }
+                    TileType::Castle if self.tick.is_multiple_of(TICK_CASTLE) => {
+                        position.nb_troops += 1
+                    }
+                    TileType::Blank if self.tick.is_multiple_of(TICK_BLANK) => {
+                        position.nb_troops += 1
+                    }
+                    _ => (),
+                },
+                TileStatus::Empty => (),
+            }
+        }
+    }
+
+    fn play_next_move(&mut self, player_uuid: &str) -> Option<PlayedMove> {
+        let (width, height) = (self.width(), self.height());
+        let attacker = self
+            .players
+            .get_mut(player_uuid)
+            .expect("no attacker in engine");
+        let next_move = attacker.queued_moves.pop_front()?;
+        let from = next_move.from.unwrap_or(attacker.xy);
+        let to = next_move.destination(from, width, height);
+        if next_move.from.is_none() {
+            // moves with an explicit origin leave the cursor where it is
+            attacker.xy = to;
+        }
+
+        // An ally's troops never leave their tile, only the ones this player just brought
+        // there can carry on
+        let origin = &self.board[from.0][from.1];
+        let passing_through = origin.player_uuid.as_deref() != Some(player_uuid)
+            && attacker.in_transit.is_some_and(|(xy, _)| xy == from);
+        let nb_moving_troops = match attacker.in_transit.take() {
+            Some((_, nb_troops)) if passing_through => {
+                let nb_troops = nb_troops.min(origin.nb_troops.saturating_sub(1));
+                match next_move.half {
+                    true => nb_troops / 2,
+                    false => nb_troops,
+                }
+            }
+            _ => next_move.nb_moving_troops(origin.nb_troops),
+        };
+        let allies = self.allies_of(player_uuid);
+        let outcome = resolve_assault(
+            player_uuid,
+            &allies,
+            passing_through,
+            &self.board,
+            from,
+            to,
+            nb_moving_troops,
+        );
+        if matches!(outcome, OutcomeAssault::SelfTroopsMove)
+            && self.board[to.0][to.1].player_uuid.as_deref() != Some(player_uuid)
+        {
+            let attacker = self
+                .players
+                .get_mut(player_uuid)
+                .expect("no attacker in engine");
+            attacker.in_transit = Some((to, nb_moving_troops));
+        }
+        self.apply_assault(player_uuid, from, to, nb_moving_troops, &outcome);
+        Some(PlayedMove {
+            player_uuid: player_uuid.to_string(),
+            from,
+            player_move: next_move,
+            outcome,
+        })
+    }
+
+    fn apply_assault(
+        &mut self,
+        attacker_uuid: &str,
+        from: (usize, usize),
+        to: (usize, usize),
+        nb_moving_troops: usize,
+        outcome: &OutcomeAssault,
+    ) {
+        let board = &mut self.board;
+        match outcome {
+            OutcomeAssault::AttackingSameTile => (),
+            OutcomeAssault::BlockedByMountain => (),
+            OutcomeAssault::NotEnoughTroops => (),
+            OutcomeAssault::TileNotOwned => (),
+            OutcomeAssault::SelfTroopsMove => {
+                board[to.0][to.1].nb_troops += nb_moving_troops;
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+            }
+            OutcomeAssault::ConquerEmpty => {
+                board[to.0][to.1] = Tile {
+                    status: TileStatus::Occupied,
+                    tile_type: board[to.0][to.1].tile_type.clone(),
+                    player_uuid: Some(attacker_uuid.to_string()),
+                    nb_troops: nb_moving_troops,
+                };
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+            }
+            OutcomeAssault::Tie => {
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+                board[to.0][to.1].nb_troops = 0;
+            }
+            OutcomeAssault::Victory(nb_remaining) => {
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+                board[to.0][to.1] = Tile {
+                    status: TileStatus::Occupied,
+                    tile_type: board[to.0][to.1].tile_type.clone(),
+                    player_uuid: Some(attacker_uuid.to_string()),
+                    nb_troops: *nb_remaining,
+                };
+            }
+            OutcomeAssault::CapturedKingdom(loser_uuid, nb_remaining) => {
+                // the kingdom becomes a castle and every tile of the loser goes to the attacker
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+                board[to.0][to.1] = Tile {
+                    status: TileStatus::Occupied,
+                    tile_type: TileType::Castle,
+                    player_uuid: Some(attacker_uuid.to_string()),
+                    nb_troops: *nb_remaining,
+                };
+                for position in board.iter_mut().flatten() {
+                    if position.player_uuid.as_ref() == Some(loser_uuid) {
+                        position.player_uuid = Some(attacker_uuid.to_string());
+                    }
+                }
+            }
+            OutcomeAssault::Defeat(defensive_losses) => {
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+                board[to.0][to.1].nb_troops = *defensive_losses;
+            }
+            OutcomeAssault::VictoryCastle(nb_remaining) => {
+                board[from.0][from.1].nb_troops -= nb_moving_troops;
+                board[to.0][to.1] = Tile {
+                    status: TileStatus::Occupied,
+                    tile_type: board[to.0][to.1].tile_type.clone(),
+                    player_uuid: Some(attacker_uuid.to_string()),
+                    nb_troops: *nb_remaining,
+                };
+            }
+        }
+    }
+
+    // What a player is allowed to see : his own tiles and their direct neighbours,
+    // the rest is hidden, with castles and kingdoms disguised as mountains
+    pub fn fog_of_war_view(&self, player_uuid: &str) -> Vec<Vec<TileUpdate>> {
+        let (width, height) = (self.width(), self.height());
+        let mut personal_board_game: Vec<Vec<TileUpdate>> = vec![];
+        for i in 0..width {
+            let mut column = vec![];
+            for j in 0..height {
+                let hidden_type = match self.board[i][j].tile_type {
+                    TileType::Blank => TileType::Blank,
+                    TileType::Kingdom => TileType::Mountain,
+                    TileType::Castle => TileType::Mountain,
+                    TileType::Mountain => TileType::Mountain,
+                };
+                column.push(TileUpdate {
+                    status: TileStatus::Empty,
+                    tile_type: hidden_type,
+                    player_name: None,
+                    nb_troops: 0,
+                    hidden: true,
+                })
+            }
+            personal_board_game.push(column);
+        }
+        // allies share their vision
+        let allies = self.allies_of(player_uuid);
+        for i in 0..width {
+            for j in 0..height {
+                if !self.board[i][j]
+                    .player_uuid
+                    .as_ref()
+                    .is_some_and(|uuid| allies.contains(uuid))
+                {
+                    continue;
+                }
+                for (x, y) in self.surroundings((i, j)) {
+                    personal_board_game[x][y]
+                        .reveal(&self.board[x][y], self.occupier_name(&self.board[x][y]));
+                }
+            }
+        }
+        personal_board_game
+    }
+
+    // Everything revealed, for replays
+    pub fn full_view(&self) -> Vec<Vec<TileUpdate>> {
+        self.board
+            .iter()
+            .map(|column| {
+                column
+                    .iter()
+                    .map(|tile| TileUpdate {
+                        status: tile.status.clone(),
+                        tile_type: tile.tile_type.clone(),
+                        player_name: self.occupier_name(tile),
+                        nb_troops: tile.nb_troops,
+                        hidden: false,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The tile itself and its 8 neighbours, clamped to the board
+    fn surroundings(&self, xy: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        let (max_x, max_y) = (self.width() - 1, self.height() - 1);
+        (xy.0.saturating_sub(1)..=(xy.0 + 1).min(max_x)).flat_map(move |x| {
+            (xy.1.saturating_sub(1)..=(xy.1 + 1).min(max_y)).map(move |y| (x, y))
+        })
+    }
+
+    fn occupier_name(&self, tile: &Tile) -> Option<String> {
+        tile.player_uuid.as_ref().map(|uuid| {
+            self.players
+                .get(uuid)
+                .expect("couldn't find player")
+                .name
+                .clone()
+        })
+    }
+}
+
+pub fn resolve_assault(
+    attacker_uuid: &str,
+    allies: &HashSet<String>, // uuids of the attacker and their teammates
+    passing_through: bool,    // the troops were left on an allied tile by the previous move
+    board: &[Vec<Tile>],
+    attacker_xy: (usize, usize),
+    defender_xy: (usize, usize),
+    nb_attacking_troops: usize,
+) -> OutcomeAssault {
+    if attacker_xy.0 == defender_xy.0 && attacker_xy.1 == defender_xy.1 {
+        return OutcomeAssault::AttackingSameTile;
+    }
+    let attacking_board = &board[attacker_xy.0][attacker_xy.1];
+    let defending_board = &board[defender_xy.0][defender_xy.1];
+    let nb_defending_troops = defending_board.nb_troops;
+    if nb_attacking_troops == 0 {
+        return OutcomeAssault::NotEnoughTroops;
+    }
+    if defending_board.tile_type == TileType::Mountain {
+        return OutcomeAssault::BlockedByMountain;
+    }
+    let is_allied = |tile: &Tile| {
+        tile.player_uuid
+            .as_ref()
+            .is_some_and(|uuid| allies.contains(uuid))
+    };
+    // If a position move in the queue was stolen, the player_uuid of the attacking
+    // tile won't be the real attacker's, and the attack forbidden.
+    // Troops can go through allied tiles, but never take the ally's own troops
+    let is_own = attacking_board.player_uuid.as_deref() == Some(attacker_uuid);
+    let is_crossing = passing_through && is_allied(attacking_board);
+    if !is_own && !is_crossing {
+        return OutcomeAssault::TileNotOwned;
+    }
+    if is_allied(defending_board) {
+        return OutcomeAssault::SelfTroopsMove;
+    }
+
+    match defending_board.status {
+        TileStatus::Occupied => match nb_attacking_troops.cmp(&nb_defending_troops) {
+            Ordering::Equal => OutcomeAssault::Tie,
+            Ordering::Greater => {
+                let loser_uuid = defending_board
+                    .player_uuid
+                    .clone()
+                    .expect("no defender name on attacked tile");
+                let nb_remaining = nb_attacking_troops - nb_defending_troops;
+                match defending_board.tile_type {
+                    TileType::Kingdom => OutcomeAssault::CapturedKingdom(loser_uuid, nb_remaining),
+                    _ => OutcomeAssault::Victory(nb_remaining),
+                }
+            }
+            Ordering::Less => OutcomeAssault::Defeat(nb_defending_troops - nb_attacking_troops),
+        },
+        TileStatus::Empty if defending_board.tile_type == TileType::Castle => {
+            match nb_attacking_troops.cmp(&nb_defending_troops) {
+                Ordering::Equal => OutcomeAssault::Tie,
+                Ordering::Greater => {
+                    OutcomeAssault::VictoryCastle(nb_attacking_troops - nb_defending_troops)
+                }
+                Ordering::Less => OutcomeAssault::Defeat(nb_defending_troops - nb_attacking_troops),
+            }
+        }
+        TileStatus::Empty => OutcomeAssault::ConquerEmpty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_board(width: usize, height: usize) -> Vec<Vec<Tile>> {
+        vec![vec![Tile::default(); height]; width]
+    }
+
+    fn occupied(player_uuid: &str, tile_type: TileType, nb_troops: usize) -> Tile {
+        Tile {
+            status: TileStatus::Occupied,
+            tile_type,
+            player_uuid: Some(player_uuid.to_string()),
+            nb_troops,
+        }
+    }
+
+    fn solo(player_uuid: &str) -> HashSet<String> {
+        HashSet::from([player_uuid.to_string()])
+    }
+
+    // alice "a" and bob "b", one kingdom each
+    fn duel(board: Vec<Vec<Tile>>, a_xy: (usize, usize), b_xy: (usize, usize)) -> GameEngine {
+        let mut engine = GameEngine::new(board);
+        engine.add_player(
+            "a".to_string(),
+            "alice".to_string(),
+            Color::Red,
+            a_xy,
+            None,
+            false,
+        );
+        engine.add_player(
+            "b".to_string(),
+            "bob".to_string(),
+            Color::Blue,
+            b_xy,
+            None,
+            false,
+        );
+        engine
+    }
+
+    fn everyone(engine: &GameEngine) -> TickInputs {
+        TickInputs {
+            active_players: engine.players.keys().cloned().collect(),
+        }
+    }
+
+    fn move_to(direction: Direction) -> PlayerMove {
+        PlayerMove {
+            direction,
+            half: false,
+            from: None,
+        }
+    }
+
+    #[test]
+    fn assault_rejected_without_effect() {
+        let mut board = empty_board(3, 1);
+        board[0][0] = occupied("a", TileType::Kingdom, 5);
+        board[1][0].tile_type = TileType::Mountain;
+        board[2][0] = occupied("b", TileType::Blank, 5);
+        let allies = solo("a");
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (0, 0), (0, 0), 4),
+            OutcomeAssault::AttackingSameTile
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (0, 0), (1, 0), 4),
+            OutcomeAssault::BlockedByMountain
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (2, 0), (1, 0), 0),
+            OutcomeAssault::NotEnoughTroops
+        ));
+        board[1][0] = Tile::default();
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (2, 0), (1, 0), 4),
+            OutcomeAssault::TileNotOwned
+        ));
+    }
+
+    #[test]
+    fn assault_on_own_and_neutral_tiles() {
+        let mut board = empty_board(4, 1);
+        board[0][0] = occupied("a", TileType::Kingdom, 20);
+        board[1][0] = occupied("a", TileType::Blank, 1);
+        board[3][0].tile_type = TileType::Castle;
+        board[3][0].nb_troops = 15;
+        let allies = solo("a");
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (0, 0), (1, 0), 19),
+            OutcomeAssault::SelfTroopsMove
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (1, 0), (2, 0), 1),
+            OutcomeAssault::ConquerEmpty
+        ));
+        board[2][0] = occupied("a", TileType::Blank, 20);
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (2, 0), (3, 0), 19),
+            OutcomeAssault::VictoryCastle(4)
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (2, 0), (3, 0), 15),
+            OutcomeAssault::Tie
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (2, 0), (3, 0), 10),
+            OutcomeAssault::Defeat(5)
+        ));
+    }
+
+    #[test]
+    fn assault_on_enemy_tiles() {
+        let mut board = empty_board(3, 1);
+        board[0][0] = occupied("b", TileType::Kingdom, 6);
+        board[1][0] = occupied("a", TileType::Blank, 20);
+        board[2][0] = occupied("b", TileType::Blank, 6);
+        let allies = solo("a");
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (1, 0), (2, 0), 8),
+            OutcomeAssault::Victory(2)
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (1, 0), (2, 0), 6),
+            OutcomeAssault::Tie
+        ));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &board, (1, 0), (2, 0), 2),
+            OutcomeAssault::Defeat(4)
+        ));
+        match resolve_assault("a", &allies, false, &board, (1, 0), (0, 0), 10) {
+            OutcomeAssault::CapturedKingdom(loser_uuid, nb_remaining) => {
+                assert_eq!((loser_uuid.as_str(), nb_remaining), ("b", 4));
+            }
+            outcome => panic!("kingdom not captured : {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn troops_grow_at_the_pace_of_their_tile() {
+        let mut board = empty_board(5, 5);
+        board[1][0] = occupied("a", TileType::Blank, 1);
+        board[2][0] = occupied("a", TileType::Castle, 15);
+        board[3][0].tile_type = TileType::Castle; // neutral castles keep their garrison
+        board[3][0].nb_troops = 15;
+        let mut engine = duel(board, (0, 0), (4, 4));
+        let inputs = everyone(&engine);
+        for _ in 0..TICK_BLANK {
+            engine.step(&inputs);
+        }
+        assert_eq!(engine.board[0][0].nb_troops, 1 + TICK_BLANK / TICK_KINGDOM);
+        assert_eq!(engine.board[1][0].nb_troops, 2);
+        assert_eq!(engine.board[2][0].nb_troops, 15 + TICK_BLANK / TICK_CASTLE);
+        assert_eq!(engine.board[3][0].nb_troops, 15);
+    }
+
+    #[test]
+    fn moves_leave_one_troop_behind() {
+        let mut engine = duel(empty_board(5, 5), (0, 0), (4, 4));
+        engine.board[0][0].nb_troops = 9;
+        engine.queue_move("a", move_to(Direction::Right));
+        let outcome = engine.step(&everyone(&engine));
+        assert!(matches!(
+            outcome.played_moves[0].outcome,
+            OutcomeAssault::ConquerEmpty
+        ));
+        assert_eq!(engine.board[0][0].nb_troops, 1);
+        assert_eq!(engine.board[1][0].player_uuid.as_deref(), Some("a"));
+        assert_eq!(engine.board[1][0].nb_troops, 9);
+        assert_eq!(engine.players["a"].xy, (1, 0));
+        assert_eq!(outcome.status, GameStatus::Running);
+    }
+
+    #[test]
+    fn capturing_a_kingdom_eliminates_its_owner() {
+        let mut engine = duel(empty_board(2, 1), (0, 0), (1, 0));
+        engine.board[0][0].nb_troops = 10;
+        engine.queue_move("a", move_to(Direction::Right));
+        let outcome = engine.step(&everyone(&engine));
+        let elimination = &outcome.eliminations[0];
+        assert_eq!(elimination.player_uuid, "b");
+        assert_eq!(elimination.by.as_deref(), Some("a"));
+        assert_eq!(elimination.placement, 2);
+        assert_eq!(outcome.status, GameStatus::Won("alice".to_string()));
+        // the captured kingdom becomes a castle of the winner
+        assert_eq!(engine.board[1][0].tile_type, TileType::Castle);
+        assert_eq!(engine.board[1][0].player_uuid.as_deref(), Some("a"));
+        assert!(engine.is_eliminated("b"));
+    }
+
+    #[test]
+    fn surrender_releases_the_territory() {
+        let mut board = empty_board(5, 5);
+        board[4][3] = occupied("b", TileType::Blank, 3);
+        let mut engine = duel(board, (0, 0), (4, 4));
+        assert!(engine.surrender("b"));
+        assert!(!engine.surrender("b"));
+        let outcome = engine.step(&everyone(&engine));
+        assert_eq!(outcome.eliminations[0].by, None);
+        assert_eq!(outcome.status, GameStatus::Won("alice".to_string()));
+        assert_eq!(engine.board[4][4].tile_type, TileType::Castle);
+        assert_eq!(engine.board[4][4].status, TileStatus::Empty);
+        assert_eq!(engine.board[4][3].player_uuid, None);
+        assert_eq!(engine.board[4][3].nb_troops, 0);
+    }
+
+    #[test]
+    fn players_eliminated_together_share_their_placement() {
+        let mut engine = duel(empty_board(5, 5), (0, 0), (4, 4));
+        engine.add_player(
+            "c".to_string(),
+            "carol".to_string(),
+            Color::Pink,
+            (0, 4),
+            None,
+            false,
+        );
+        engine.surrender("b");
+        engine.surrender("c");
+        let outcome = engine.step(&everyone(&engine));
+        let placements: Vec<usize> = outcome.eliminations.iter().map(|e| e.placement).collect();
+        assert_eq!(placements, vec![2, 2]);
+        assert_eq!(outcome.status, GameStatus::Won("alice".to_string()));
+    }
+
+    #[test]
+    fn ranking_ties_share_their_placement() {
+        let mut engine = duel(empty_board(5, 5), (0, 0), (4, 4));
+        engine.add_player(
+            "c".to_string(),
+            "carol".to_string(),
+            Color::Pink,
+            (0, 4),
+            None,
+            false,
+        );
+        engine.add_player(
+            "d".to_string(),
+            "dave".to_string(),
+            Color::Green,
+            (4, 0),
+            None,
+            false,
+        );
+        engine.board[1][0] = occupied("a", TileType::Blank, 1);
+        engine.surrender("d");
+        engine.step(&TickInputs::default());
+        let ranking: Vec<(String, usize)> = engine
+            .ranking()
+            .into_iter()
+            .map(|player| (player.name, player.placement))
+            .collect();
+        assert_eq!(ranking[0], ("alice".to_string(), 1));
+        assert_eq!(ranking[1].1, 2);
+        assert_eq!(ranking[2].1, 2);
+        assert_eq!(ranking[3], ("dave".to_string(), 4));
+    }
+
+    #[test]
+    fn a_game_without_connected_players_stops() {
+        let mut engine = duel(empty_board(5, 5), (0, 0), (4, 4));
+        let outcome = engine.step(&TickInputs::default());
+        assert_eq!(outcome.status, GameStatus::NobodyActive);
+    }
+
+    // Where the path leads, checking it never steps on a mountain
+    fn follow(engine: &GameEngine, from: (usize, usize), path: &[Direction]) -> (usize, usize) {
+        path.iter().fold(from, |xy, direction| {
+            let next = direction.destination(xy, engine.width(), engine.height());
+            assert_ne!(engine.board[next.0][next.1].tile_type, TileType::Mountain);
+            assert_ne!(next, xy);
+            next
+        })
+    }
+
+    #[test]
+    fn path_goes_around_the_mountains() {
+        let mut board = empty_board(5, 5);
+        for tile in board[1].iter_mut().take(4) {
+            tile.tile_type = TileType::Mountain;
+        }
+        let engine = duel(board, (0, 0), (4, 4));
+        let path = engine.path_to("a", (2, 0)).unwrap();
+        assert_eq!(path.len(), 10);
+        assert_eq!(follow(&engine, (0, 0), &path), (2, 0));
+    }
+
+    #[test]
+    fn path_starts_where_the_queued_moves_end() {
+        let mut engine = duel(empty_board(5, 5), (0, 0), (4, 4));
+        engine.queue_move("a", move_to(Direction::Right));
+        engine.queue_move("a", move_to(Direction::Down));
+        let path = engine.path_to("a", (3, 1)).unwrap();
+        assert_eq!(path.len(), 2);
+        assert_eq!(follow(&engine, (1, 1), &path), (3, 1));
+        assert!(engine
+            .path_to("a", (1, 1))
+            .is_some_and(|path| path.is_empty()));
+    }
+
+    #[test]
+    fn no_path_to_unreachable_tiles() {
+        let mut board = empty_board(5, 5);
+        board[2][3].tile_type = TileType::Mountain;
+        board[3][2].tile_type = TileType::Mountain;
+        board[3][4].tile_type = TileType::Mountain;
+        board[4][3].tile_type = TileType::Mountain;
+        let engine = duel(board, (0, 0), (4, 4));
+        assert!(engine.path_to("a", (3, 3)).is_none());
+        assert!(engine.path_to("a", (2, 3)).is_none()); // a mountain itself
+        assert!(engine.path_to("a", (5, 0)).is_none());
+        assert!(engine.path_to("nobody", (1, 0)).is_none());
+    }
+
+    #[test]
+    fn hidden_castles_are_avoided_like_mountains() {
+        let mut board = empty_board(5, 2);
+        board[3][0].tile_type = TileType::Castle; // out of sight, it looks like a mountain
+        board[3][0].nb_troops = 15;
+        // the corner is only reachable through the castle or bob's kingdom, hidden as well
+        let engine = duel(board, (0, 0), (4, 1));
+        assert!(engine.path_to("a", (4, 0)).is_none());
+        assert!(engine.path_to("a", (3, 1)).is_some());
+    }
+
+    // 2v2 : alice "a" and carol "c" against bob "b" and dave "d"
+    fn two_versus_two() -> GameEngine {
+        let mut engine = GameEngine::new(empty_board(8, 3));
+        for (uuid, name, color, xy, team) in [
+            ("a", "alice", Color::Red, (0, 0), 0),
+            ("b", "bob", Color::Blue, (7, 2), 1),
+            ("c", "carol", Color::Pink, (3, 0), 0),
+            ("d", "dave", Color::Green, (5, 2), 1),
+        ] {
+            engine.add_player(
+                uuid.to_string(),
+                name.to_string(),
+                color,
+                xy,
+                Some(team),
+                false,
+            );
+        }
+        engine
+    }
+
+    #[test]
+    fn allies_reinforce_each_other_instead_of_attacking() {
+        let mut engine = two_versus_two();
+        assert_eq!(
+            engine.allies_of("a"),
+            HashSet::from(["a".to_string(), "c".to_string()])
+        );
+        engine.board[2][0] = occupied("c", TileType::Blank, 3);
+        let allies = engine.allies_of("a");
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &engine.board, (0, 0), (3, 0), 5),
+            OutcomeAssault::SelfTroopsMove
+        ));
+        // the troops of an ally can't be taken
+        assert!(!engine.select("a", (2, 0)));
+        assert!(matches!(
+            resolve_assault("a", &allies, false, &engine.board, (2, 0), (2, 1), 2),
+            OutcomeAssault::TileNotOwned
+        ));
+        engine.queue_move(
+            "a",
+            PlayerMove {
+                direction: Direction::Down,
+                half: false,
+                from: Some((2, 0)),
+            },
+        );
+        let outcome = engine.step(&everyone(&engine));
+        assert!(matches!(
+            outcome.played_moves[0].outcome,
+            OutcomeAssault::TileNotOwned
+        ));
+    }
+
+    #[test]
+    fn troops_pass_through_allied_tiles() {
+        let mut engine = two_versus_two();
+        engine.board[0][0].nb_troops = 10;
+        engine.board[1][0] = occupied("c", TileType::Blank, 3);
+        engine.queue_move("a", move_to(Direction::Right));
+        engine.queue_move("a", move_to(Direction::Down));
+        let inputs = everyone(&engine);
+        engine.step(&inputs);
+        // the kingdom grew to 11 before the move, the tile stays carol's
+        assert_eq!(engine.board[1][0].player_uuid.as_deref(), Some("c"));
+        assert_eq!(engine.board[1][0].nb_troops, 13);
+        let outcome = engine.step(&inputs);
+        assert!(matches!(
+            outcome.played_moves[0].outcome,
+            OutcomeAssault::ConquerEmpty
+        ));
+        assert_eq!(engine.board[1][1].player_uuid.as_deref(), Some("a"));
+        assert_eq!(engine.board[1][1].nb_troops, 10);
+        assert_eq!(engine.board[1][0].nb_troops, 3);
+    }
+
+    #[test]
+    fn allies_share_their_vision() {
+        let engine = two_versus_two();
+        let view = engine.fog_of_war_view("a");
+        assert!(!view[4][0].hidden); // next to carol's kingdom
+        assert!(view[6][2].hidden); // next to bob's and dave's
+        assert!(!engine.fog_of_war_view("b")[6][2].hidden);
+    }
+
+    #[test]
+    fn last_team_standing_wins_together() {
+        let mut engine = two_versus_two();
+        engine.surrender("b");
+        let outcome = engine.step(&everyone(&engine));
+        assert_eq!(outcome.status, GameStatus::Running);
+        engine.surrender("d");
+        let outcome = engine.step(&everyone(&engine));
+        assert_eq!(outcome.status, GameStatus::TeamWon(0));
+        let ranking = engine.ranking();
+        let winners: HashSet<&str> = ranking
+            .iter()
+            .filter(|player| player.placement == 1)
+            .map(|player| player.name.as_str())
+            .collect();
+        assert_eq!(winners, HashSet::from(["alice", "carol"]));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::configs::app_state::AppState;
+use crate::constants::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+use crate::custom_errors::service_errors::ServiceError;
+use crate::data_access_layer::game_dal;
+use crate::requests::requests::{
+    CreateGamePlayerRequest, CreateGameRequest, GameMode, GameResponse, PaginatedResponse,
+    PaginationRequest, PlayerGameResponse,
+};
+use crate::service_layer::{
+    game_engine::{GameEngine, PlayerRanking},
+    replay_service::Replay,
+};
+use crate::utilities::responses::{response_ok, ApiResponse};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use std::sync::Arc;
+
+// A game taken out of its lobby when it ends, so that it can be saved without holding the lobby
+pub struct FinishedGame {
+    pub game_id: String,
+    pub lobby_id: usize,
+    pub player_capacity: usize,
+    pub mode: GameMode,
+    pub is_ranked: bool,
+    pub started_at: i64, // unix timestamp seconds
+    pub engine: GameEngine,
+    pub replay: Replay,
+}
+
+pub fn save_game_history(state: &Arc<AppState>, game: &FinishedGame) -> Result<(), ServiceError> {
+    let ranking = game.engine.ranking();
+    let survivors: Vec<&PlayerRanking> = ranking
+        .iter()
+        .filter(|player| player.total_positions > 0)
+        .collect();
+    let winner_name = match survivors[..] {
+        [winner] => Some(winner.name.clone()),
+        _ => None,
+    };
+    // a team wins when only its members are left, whoever of them survived
+    let winning_team = match survivors.first().and_then(|player| player.team) {
+        Some(team) if survivors.iter().all(|player| player.team == Some(team)) => Some(team),
+        _ => None,
+    };
+
+    game_dal::create_game(
+        state,
+        CreateGameRequest {
+            game_id: game.game_id.clone(),
+            lobby_id: game.lobby_id,
+            player_capacity: game.player_capacity,
+            mode: game.mode,
+            started_at: game.started_at,
+            ended_at: Utc::now().timestamp(),
+            nb_ticks: game.engine.tick,
+            winner_name,
+            winning_team,
+            players: ranking
+                .into_iter()
+                .filter(|player| !player.is_bot) // bots have no player account
+                .map(|player| CreateGamePlayerRequest {
+                    player_uuid: player.player_uuid,
+                    player_name: player.name,
+                    placement: player.placement,
+                    total_positions: player.total_positions,
+                    total_troops: player.total_troops,
+                    team: player.team,
+                })
+                .collect(),
+        },
+    )
+}
+
+pub async fn get_game(
+    State(state): State<Arc<AppState>>,
+    Path(game_id): Path<String>,
+) -> Result<(StatusCode, Json<ApiResponse<GameResponse>>), ServiceError> {
+    response_ok(Some(game_dal::get_game(&state, game_id)?))
+}
+
+pub async fn get_player_games(
+    State(state): State<Arc<AppState>>,
+    Path(player_uuid): Path<String>,
+    Query(pagination): Query<PaginationRequest>,
+) -> Result<
+    (
+        StatusCode,
+        Json<ApiResponse<PaginatedResponse<PlayerGameResponse>>>,
+    ),
+    ServiceError,
+> {
+    let page = pagination.page.unwrap_or(0);
+    let page_size = pagination
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let games = game_dal::get_player_games(
+        &state,
+        player_uuid.clone(),
+        page_size,
+        page.saturating_mul(page_size),
+    )?;
+    let total_items = game_dal::count_player_games(&state, player_uuid)?;
+
+    response_ok(Some(PaginatedResponse {
+        items: games,
+        page,
+        page_size,
+        total_items,
+    }))
+}
@@ -1,22 +1,29 @@
 use crate::{
     configs::{
         self,
-        app_state::{Lobby, LobbyStatus, Tile, TileStatus, TileType},
+        app_state::{Lobby, LobbyStatus},
+    },
+    constants::{SPECTATOR_DELAY_TICKS, TICK_GAME_INTERVAL_MS},
+    models::messages_to_clients::{
+        GameOver, GameOverReason, GameUpdate, GameUpdateDelta, PlayerElimination, RankedPlayer,
+        RejectedMove, SpectatorUpdate, TileDelta, TileUpdate, WsMessageToClient,
     },
-    constants::{TICK_BLANK, TICK_CASTLE, TICK_GAME_INTERVAL_MS, TICK_KINGDOM},
-    models::messages_to_clients::{GameUpdate, PlayerScore, TileUpdate, WsMessageToClient},
 };
 use chrono::Utc;
-use rand::Rng;
 use std::{
-    cmp::Ordering,
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{HashMap, HashSet},
     sync::Arc,
 };
 use tokio::time::{interval, Duration};
+use uuid::Uuid;
 
 use super::{
-    player_service::{Color, PlayerMove, PlayerMoves},
+    bot_service::{fill_with_bots, queue_bot_moves},
+    game_engine::{GameEngine, GameStatus, TickInputs},
+    game_history_service::{save_game_history, FinishedGame},
+    player_service::{Color, Player},
+    rating_service::update_ratings,
+    replay_service::{save_replay, Replay},
     websocket_service::global_lobbies_update,
 };
 
@@ -24,21 +31,31 @@ pub async fn game_loop(state: Arc<configs::app_state::AppState>) {
     let mut interval = interval(Duration::from_millis(TICK_GAME_INTERVAL_MS));
     loop {
         interval.tick().await; // The first tick completes immediately
-        for mutex_lobby in state.lobbies.iter() {
+        for (lobby_id, mutex_lobby) in state.lobbies.iter().enumerate() {
+            // players before the lobby, every path takes the locks in this order
+            let players = state.players.read().expect("failed to lock players");
             let mut lobby = mutex_lobby.write().expect("failed to lock lobby");
             match lobby.status {
-                LobbyStatus::AwaitingPlayers => (),
+                LobbyStatus::AwaitingPlayers => {
+                    if fill_with_bots(&mut lobby) {
+                        drop(lobby);
+                        drop(players);
+                        global_lobbies_update(state.clone());
+                    }
+                }
                 LobbyStatus::StartingSoon => {
-                    if lunch_game(state.clone(), &mut lobby) {
+                    if lunch_game(&mut lobby) {
                         drop(lobby); // global lobbies update needs to take ownership of all the lobbies
+                        drop(players);
                         global_lobbies_update(state.clone());
                     }
                 }
                 LobbyStatus::InGame => {
-                    if let Ok(is_game_finished) = tick_game(&mut lobby, state.clone()) {
+                    if let Ok(is_game_finished) = tick_game(&mut lobby, &players) {
                         if is_game_finished {
-                            end_lobby_game(&mut lobby, state.clone());
-                            drop(lobby);
+                            drop(lobby); // ending the game takes the locks again, players first
+                            drop(players);
+                            end_lobby_game(&state, lobby_id);
                             global_lobbies_update(state.clone());
                         }
                     }
@@ -48,30 +65,47 @@ pub async fn game_loop(state: Arc<configs::app_state::AppState>) {
     }
 }
 
-fn lunch_game(state: Arc<configs::app_state::AppState>, lobby: &mut Lobby) -> bool {
+fn lunch_game(lobby: &mut Lobby) -> bool {
     if lobby.next_starting_time - Utc::now().timestamp() <= 0 {
-        lobby.status = LobbyStatus::InGame;
-        let mut unavailable_colors = vec![];
-        let mut players = state.players.write().expect("failed to lock player");
-        for (player_uuid, _player_name) in lobby.players.iter() {
-            let new_player_color = Color::pick_available_color(&unavailable_colors)
-                .expect("no player color available");
-            let (x, y) = pick_available_starting_coordinates(&lobby.board_game);
-            if let Some(player) = players.get_mut(&player_uuid.clone()) {
-                // the player could leave the lobby while the game is lunching..
-                player.queued_moves = VecDeque::new();
-                player.color = new_player_color.clone();
-                unavailable_colors.push(new_player_color.clone());
-                player.xy = (x, y);
-            }
-            lobby.board_game[x][y] = Tile {
-                // still add him in the map, will be displayed as inactive
-                status: TileStatus::Occupied,
-                tile_type: TileType::Kingdom,
-                nb_troops: 1,
-                player_uuid: Some(player_uuid.clone()),
-            };
+        let game_id = Uuid::now_v7().to_string();
+        let replay = Replay::new(
+            game_id.clone(),
+            lobby.lobby_id,
+            lobby.seed,
+            &lobby.game_engine.board,
+        );
+        // sorted so that the same seed always gives the same spawns and colors,
+        // still add the players who left while the game was lunching, they will be displayed as inactive
+        let mut lobby_players: Vec<(String, String, bool)> = lobby
+            .players
+            .iter()
+            .map(|(uuid, name)| (uuid.clone(), name.clone(), lobby.bots.contains_key(uuid)))
+            .collect();
+        lobby_players.sort();
+        let nb_teams = lobby
+            .team_size
+            .map(|team_size| lobby.player_capacity / team_size);
+        if let Err(error) = place_players(
+            &mut lobby.game_engine,
+            &lobby.spawns,
+            &lobby_players,
+            nb_teams,
+        ) {
+            // the players get a generated board instead, the launch is tried again next tick
+            println!(
+                "lobby {} can't start on its map : {}",
+                lobby.lobby_id, error
+            );
+            lobby.custom_map = None;
+            lobby.generate_new_board();
+            return false;
         }
+        lobby.status = LobbyStatus::InGame;
+        println!("lobby {} starting with seed {}", lobby.lobby_id, lobby.seed);
+        lobby.game_id = game_id;
+        lobby.game_started_at = Utc::now().timestamp();
+        lobby.replay = replay;
+        lobby.replay.record_roster(&lobby.game_engine);
         let _ = lobby
             .lobby_broadcast
             .send(WsMessageToClient::GameStarted(lobby.lobby_id));
@@ -82,352 +116,358 @@ fn lunch_game(state: Arc<configs::app_state::AppState>, lobby: &mut Lobby) -> bo
     }
 }
 
-fn tick_game(lobby: &mut Lobby, state: Arc<configs::app_state::AppState>) -> Result<bool, String> {
-    lobby.tick += 1;
-    for position in lobby.board_game.iter_mut().flatten() {
-        match position.status {
-            TileStatus::Occupied => match position.tile_type {
-                TileType::Kingdom if lobby.tick % TICK_KINGDOM == 0 => position.nb_troops += 1,
-                TileType::Castle if lobby.tick % TICK_CASTLE == 0 => position.nb_troops += 1,
-                TileType::Blank if lobby.tick % TICK_BLANK == 0 => position.nb_troops += 1,
-                TileType::Mountain => (),
-                _ => (),
-            },
-            TileStatus::Empty => (),
-        }
-    }
-    let mut players = state.players.write().expect("failed to lock players");
-    let mut scoreboard: HashMap<String, PlayerScore> = HashMap::new();
-    let width_game = lobby.board_game.len();
-    let height_game = lobby.board_game[0].len();
-
-    'loop_attackers: for (player_uuid, player_name) in lobby.players.iter() {
-        scoreboard.insert(
-            player_name.clone(),
-            PlayerScore {
-                total_positions: 0,
-                total_troops: 0,
-                color: Color::Grey,
-            },
-        );
-        if let Some(attacker) = players.get_mut(player_uuid) {
-            match attacker.playing_in_lobby {
-                None => continue 'loop_attackers, // if the player left the webpage
-                Some(lobby_id) => {
-                    if lobby_id != lobby.lobby_id {
-                        // The player refreshed and went inside another game
-                        continue 'loop_attackers;
-                    }
-                }
-            };
-            scoreboard
-                .get_mut(&attacker.name.clone())
-                .expect("no attacker name in score board")
-                .color = attacker.color.clone();
+fn tick_game(lobby: &mut Lobby, players: &HashMap<String, Player>) -> Result<bool, String> {
+    let now = Utc::now().timestamp();
+    lobby.disconnected.retain(|_, deadline| *deadline >= now);
+    // the player could have left the webpage, or refreshed and went inside another game
+    let lobby_members: Vec<_> = lobby
+        .players
+        .keys()
+        .filter_map(|player_uuid| players.get(player_uuid))
+        .filter(|player| player.playing_in_lobby == Some(lobby.lobby_id))
+        .collect();
+    let inputs = TickInputs {
+        active_players: active_players(lobby, players),
+    };
 
-            if let Some(next_move) = attacker.queued_moves.pop_front() {
-                let mut attacked_x = attacker.xy.0;
-                let mut attacked_y = attacker.xy.1;
-                match next_move {
-                    PlayerMove::Left => {
-                        attacked_x = attacker.xy.0.saturating_sub(1);
-                    }
-                    PlayerMove::Right => {
-                        attacked_x = (attacker.xy.0 + 1).min(width_game - 1);
-                    }
-                    PlayerMove::Up => {
-                        attacked_y = attacker.xy.1.saturating_sub(1);
-                    }
-                    PlayerMove::Down => {
-                        attacked_y = (attacker.xy.1 + 1).min(height_game - 1);
-                    }
-                }
-                match resolve_assault(
-                    attacker.uuid.clone(),
-                    &lobby.board_game,
-                    attacker.xy,
-                    (attacked_x, attacked_y),
-                ) {
-                    OutcomeAssault::AttackingSameTile => (),
-                    OutcomeAssault::BlockedByMountain => (),
-                    OutcomeAssault::NotEnoughTroops => (),
-                    OutcomeAssault::TileNotOwned => (),
-                    OutcomeAssault::SelfTroopsMove => {
-                        lobby.board_game[attacked_x][attacked_y].nb_troops +=
-                            lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops - 1;
-                        lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops = 1;
-                    }
-                    OutcomeAssault::ConquerEmpty => {
-                        lobby.board_game[attacked_x][attacked_y] = Tile {
-                            status: TileStatus::Occupied,
-                            tile_type: lobby.board_game[attacked_x][attacked_y].tile_type.clone(),
-                            player_uuid: Some(attacker.uuid.clone()),
-                            nb_troops: lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops - 1,
-                        };
-                        lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops = 1;
-                    }
-                    OutcomeAssault::Tie => {
-                        lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops = 1;
-                        lobby.board_game[attacked_x][attacked_y].nb_troops = 0;
-                    }
-                    OutcomeAssault::Victory(loser_uuid, nb_remaining) => {
-                        lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops = 1;
-                        lobby.board_game[attacked_x][attacked_y] = Tile {
-                            status: TileStatus::Occupied,
-                            tile_type: lobby.board_game[attacked_x][attacked_y].tile_type.clone(),
-                            player_uuid: Some(attacker.uuid.clone()),
-                            nb_troops: nb_remaining,
-                        };
-                        if lobby.board_game[attacked_x][attacked_y].tile_type == TileType::Kingdom {
-                            lobby.board_game[attacked_x][attacked_y].tile_type = TileType::Castle;
-                            for position in lobby.board_game.iter_mut().flatten() {
-                                if let Some(occupier_uuid) = position.player_uuid.clone() {
-                                    if occupier_uuid == loser_uuid {
-                                        position.player_uuid = Some(attacker.uuid.clone());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    OutcomeAssault::Defeat(defensive_losses) => {
-                        lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops = 1;
-                        lobby.board_game[attacked_x][attacked_y].nb_troops = defensive_losses;
-                    }
-                    OutcomeAssault::VictoryCastle(nb_remaining) => {
-                        lobby.board_game[attacker.xy.0][attacker.xy.1].nb_troops = 1;
-                        lobby.board_game[attacked_x][attacked_y] = Tile {
-                            status: TileStatus::Occupied,
-                            tile_type: lobby.board_game[attacked_x][attacked_y].tile_type.clone(),
-                            player_uuid: Some(attacker.uuid.clone()),
-                            nb_troops: nb_remaining,
-                        };
-                    }
-                }
-                attacker.xy = (attacked_x, attacked_y);
+    queue_bot_moves(&mut lobby.game_engine, &lobby.bots, &mut rand::thread_rng());
+    let outcome = lobby.game_engine.step(&inputs);
+    lobby
+        .replay
+        .record_tick(&lobby.game_engine, &inputs, &outcome);
 
-                println!("next move: {:?}", next_move);
-            }
-        }
+    for elimination in outcome.eliminations.iter() {
+        let _ = lobby
+            .lobby_broadcast
+            .send(WsMessageToClient::PlayerEliminated(PlayerElimination {
+                name: lobby.game_engine.players[&elimination.player_uuid]
+                    .name
+                    .clone(),
+                by: elimination
+                    .by
+                    .as_ref()
+                    .map(|uuid| lobby.game_engine.players[uuid].name.clone()),
+                tick: elimination.tick,
+                placement: elimination.placement,
+            }));
     }
 
-    let mut remaining_players = HashSet::new();
-    for position in lobby.board_game.iter().flatten() {
-        if let Some(occupier_uuid) = position.player_uuid.clone() {
-            let player_name = lobby
-                .players
-                .get(&occupier_uuid)
-                .expect("couldn't find player");
-            remaining_players.insert(player_name);
-            scoreboard.entry(player_name.clone()).and_modify(|score| {
-                score.total_positions += 1;
-                score.total_troops += position.nb_troops;
+    for player in lobby_members.iter() {
+        let board_game = lobby.game_engine.fog_of_war_view(&player.uuid);
+        let moves = lobby
+            .game_engine
+            .player_moves(&player.uuid)
+            .expect("no moves for player in game");
+        let rejected_move = outcome
+            .played_moves
+            .iter()
+            .find(|played| played.player_uuid == player.uuid)
+            .and_then(|played| {
+                played.outcome.rejection().map(|reason| RejectedMove {
+                    from: played.from,
+                    player_move: played.player_move,
+                    reason,
+                })
             });
-        }
+        // bot accounts always get the whole board, they don't have to keep track of it
+        let sent_board = lobby
+            .sent_boards
+            .get(&player.uuid)
+            .filter(|_| !player.is_bot);
+        let update = match sent_board {
+            Some((base_tick, sent_board)) => WsMessageToClient::GameUpdateDelta(GameUpdateDelta {
+                tiles: board_delta(sent_board, &board_game),
+                score_board: outcome.score_board.clone(),
+                moves,
+                rejected_move,
+                base_tick: *base_tick,
+                tick: outcome.tick,
+            }),
+            None => WsMessageToClient::GameUpdate(GameUpdate {
+                board_game: board_game.clone(),
+                score_board: outcome.score_board.clone(),
+                moves,
+                rejected_move,
+                tick: outcome.tick,
+            }),
+        };
+        let _ = player.personal_tx.send(update);
+        lobby
+            .sent_boards
+            .insert(player.uuid.clone(), (outcome.tick, board_game));
     }
 
-    let mut nb_active = 0; // todo : count differently with filter
-    for (_, score) in scoreboard.iter() {
-        if score.color != Color::Grey {
-            nb_active += 1;
+    lobby.spectator_frames.push_back(SpectatorUpdate {
+        board_game: lobby.game_engine.full_view(),
+        score_board: outcome.score_board.clone(),
+        tick: outcome.tick,
+    });
+    // once the game is over there is nothing left to hide, the spectators get the last frames
+    // right away so they see the end of the game before the game over
+    let is_over = outcome.status != GameStatus::Running;
+    while lobby.spectator_frames.len() > SPECTATOR_DELAY_TICKS
+        || (is_over && !lobby.spectator_frames.is_empty())
+    {
+        let frame = lobby
+            .spectator_frames
+            .pop_front()
+            .expect("no spectator frame");
+        for spectator in lobby.spectators.iter().filter_map(|uuid| players.get(uuid)) {
+            let _ = spectator
+                .personal_tx
+                .send(WsMessageToClient::SpectatorUpdate(frame.clone()));
         }
     }
-    for (_, player) in players.iter() {
-        let mut personal_board_game: Vec<Vec<TileUpdate>> = vec![];
-        let width = lobby.board_game.len();
-        let height = lobby.board_game[0].len();
-        for i in 0..width {
-            let mut column = vec![];
-            for j in 0..height {
-                let hidden_type = match lobby.board_game[i][j].tile_type {
-                    TileType::Blank => TileType::Blank,
-                    TileType::Kingdom => TileType::Mountain,
-                    TileType::Castle => TileType::Mountain,
-                    TileType::Mountain => TileType::Mountain,
-                };
-                column.push(TileUpdate {
-                    status: TileStatus::Empty,
-                    tile_type: hidden_type,
-                    player_name: None,
-                    nb_troops: 0,
-                    hidden: true,
-                })
-            }
-            personal_board_game.push(column);
-        }
-        for i in 0..width {
-            for j in 0..height {
-                if let Some(uuid) = lobby.board_game[i][j].player_uuid.clone() {
-                    if uuid == player.uuid {
-                        let min_w = i.saturating_sub(1);
-                        let min_h = j.saturating_sub(1);
-                        let max_w = (i + 1).min(width - 1);
-                        let max_h = (j + 1).min(height - 1);
 
-                        personal_board_game[min_w][min_h]
-                            .from_game_tile(&lobby.board_game[min_w][min_h], &lobby.players);
-                        personal_board_game[min_w][j]
-                            .from_game_tile(&lobby.board_game[min_w][j], &lobby.players);
-                        personal_board_game[min_w][max_h]
-                            .from_game_tile(&lobby.board_game[min_w][max_h], &lobby.players);
-                        personal_board_game[i][min_h]
-                            .from_game_tile(&lobby.board_game[i][min_h], &lobby.players);
-                        personal_board_game[i][j]
-                            .from_game_tile(&lobby.board_game[i][j], &lobby.players);
-                        personal_board_game[i][max_h]
-                            .from_game_tile(&lobby.board_game[i][max_h], &lobby.players);
-                        personal_board_game[max_w][min_h]
-                            .from_game_tile(&lobby.board_game[max_w][min_h], &lobby.players);
-                        personal_board_game[max_w][j]
-                            .from_game_tile(&lobby.board_game[max_w][j], &lobby.players);
-                        personal_board_game[max_w][max_h]
-                            .from_game_tile(&lobby.board_game[max_w][max_h], &lobby.players);
-                    }
-                }
-            }
+    let (reason, winner, winning_team) = match outcome.status {
+        GameStatus::Running => return Ok(false),
+        GameStatus::Won(winner_name) => {
+            (GameOverReason::LastPlayerStanding, Some(winner_name), None)
         }
-
-        let _ = player
+        GameStatus::TeamWon(team) => (GameOverReason::LastTeamStanding, None, Some(team)),
+        GameStatus::NoSurvivor => (GameOverReason::NoSurvivor, None, None),
+        GameStatus::NobodyActive => (GameOverReason::NobodyActive, None, None),
+    };
+    let game_over = GameOver {
+        reason,
+        winner,
+        winning_team,
+        ranking: lobby
+            .game_engine
+            .ranking()
+            .into_iter()
+            .map(|player| RankedPlayer {
+                name: player.name,
+                placement: player.placement,
+                total_positions: player.total_positions,
+                total_troops: player.total_troops,
+                team: player.team,
+                is_bot: player.is_bot,
+            })
+            .collect(),
+    };
+    for spectator in lobby.spectators.iter().filter_map(|uuid| players.get(uuid)) {
+        let _ = spectator
             .personal_tx
-            .send(WsMessageToClient::GameUpdate(GameUpdate {
-                board_game: personal_board_game,
-                score_board: scoreboard.clone(),
-                moves: PlayerMoves {
-                    queued_moves: player.queued_moves.clone(),
-                    xy: player.xy,
-                },
-                tick: lobby.tick,
-            }));
+            .send(WsMessageToClient::GameOver(game_over.clone()));
     }
+    let _ = lobby
+        .lobby_broadcast
+        .send(WsMessageToClient::GameOver(game_over));
+    Ok(true)
+}
 
-    println!("aaaaa 1 {} ", nb_active);
-    println!("aaaaa 2 {} ", remaining_players.len());
-    match remaining_players.len() {
-        1 => {
-            let _ = lobby
-                .lobby_broadcast
-                .send(WsMessageToClient::WinnerAnnouncement(
-                    remaining_players
-                        .iter()
-                        .next()
-                        .expect("no remaining player to win")
-                        .to_string(),
-                ));
-            Ok(true)
-        }
-        0 => {
-            let _ = lobby
-                .lobby_broadcast
-                .send(WsMessageToClient::WinnerAnnouncement("".to_string())); // todo : handle with none
-            Ok(true)
+fn board_delta(previous: &[Vec<TileUpdate>], current: &[Vec<TileUpdate>]) -> Vec<TileDelta> {
+    let mut delta = vec![];
+    for (x, (previous_column, current_column)) in previous.iter().zip(current).enumerate() {
+        for (y, (previous_tile, current_tile)) in
+            previous_column.iter().zip(current_column).enumerate()
+        {
+            if previous_tile != current_tile {
+                delta.push(TileDelta(x, y, current_tile.clone()));
+            }
         }
-        _ if nb_active == 0 => Ok(true), // player still occupying some tiles, but nobody is active
-        _ => Ok(false),
     }
+    delta
 }
 
-pub fn end_lobby_game(lobby: &mut Lobby, state: Arc<configs::app_state::AppState>) {
-    let mut all_players = state
+// Players connected to the game, plus the ones who dropped and can still come back, and the bots
+pub fn active_players(lobby: &Lobby, players: &HashMap<String, Player>) -> HashSet<String> {
+    lobby
         .players
-        .write()
-        .expect("failed to lock players end game");
+        .keys()
+        .filter(|player_uuid| {
+            lobby.bots.contains_key(*player_uuid)
+                || lobby.disconnected.contains_key(*player_uuid)
+                || players
+                    .get(*player_uuid)
+                    .is_some_and(|player| player.playing_in_lobby == Some(lobby.lobby_id))
+        })
+        .cloned()
+        .collect()
+}
 
-    for (player_uuid, _) in lobby.players.iter() {
-        for (_, player) in all_players.iter_mut() {
-            if player_uuid.clone() == player.uuid {
-                if let Some(already_in_lobby_id) = player.playing_in_lobby {
-                    if already_in_lobby_id == lobby.lobby_id {
-                        // only reset if not already started another game (after leaving the current one)
-                        player.playing_in_lobby = None;
-                    }
+// The lobby is reset under the locks, the game is saved once they are released
+pub fn end_lobby_game(state: &Arc<configs::app_state::AppState>, lobby_id: usize) {
+    let (finished_game, lobby_broadcast) = {
+        let mut all_players = state
+            .players
+            .write()
+            .expect("failed to lock players end game");
+        let mut lobby = state.lobbies[lobby_id]
+            .write()
+            .expect("failed to lock lobby end game");
+
+        for (player_uuid, _) in lobby.players.iter() {
+            if let Some(player) = all_players.get_mut(player_uuid) {
+                if player.playing_in_lobby == Some(lobby.lobby_id) {
+                    // only reset if not already started another game (after leaving the current one)
+                    player.playing_in_lobby = None;
                 }
             }
         }
-    }
-    lobby.generate_new_board();
-    lobby.status = LobbyStatus::AwaitingPlayers;
-    lobby.players = HashMap::new();
-    lobby.tick = 0;
-}
+        // spectators are detached, they can pick another game to watch
+        for spectator_uuid in lobby.spectators.drain() {
+            if let Some(spectator) = all_players.get(&spectator_uuid) {
+                let _ = spectator
+                    .personal_tx
+                    .send(WsMessageToClient::SpectatingEnded(lobby_id));
+            }
+        }
+        lobby.spectator_frames.clear();
+        let _ = lobby
+            .lobby_broadcast
+            .send(WsMessageToClient::GameSeed(lobby.seed));
+        let finished_game = FinishedGame {
+            game_id: lobby.game_id.clone(),
+            lobby_id,
+            player_capacity: lobby.player_capacity,
+            mode: lobby.mode(),
+            is_ranked: lobby.is_ranked,
+            started_at: lobby.game_started_at,
+            engine: std::mem::replace(&mut lobby.game_engine, GameEngine::new(vec![])),
+            replay: std::mem::take(&mut lobby.replay),
+        };
+        lobby.custom_map = None; // the next players pick their own map
+        lobby.generate_new_board();
+        lobby.status = LobbyStatus::AwaitingPlayers;
+        lobby.players = HashMap::new();
+        lobby.bots = HashMap::new();
+        lobby.disconnected = HashMap::new();
+        lobby.sent_boards = HashMap::new();
+        (finished_game, lobby.lobby_broadcast.clone())
+    };
 
-pub fn pick_available_starting_coordinates(board: &Vec<Vec<Tile>>) -> (usize, usize) {
-    let mut rng = rand::thread_rng();
-    loop {
-        let x = rng.gen_range(0..board.len());
-        let y = rng.gen_range(0..board[0].len());
-        match board[x][y].status {
-            TileStatus::Empty => return (x, y),
-            _ => continue,
+    match save_game_history(state, &finished_game) {
+        Ok(()) if finished_game.is_ranked => match update_ratings(state, &finished_game) {
+            Ok(changes) => {
+                let _ = lobby_broadcast.send(WsMessageToClient::RatingsUpdate(changes));
+            }
+            Err(e) => println!(
+                "failed to update ratings of game {} : {:?}",
+                finished_game.game_id, e
+            ),
+        },
+        Ok(()) => (),
+        Err(e) => println!(
+            "failed to save history of game {} : {:?}",
+            finished_game.game_id, e
+        ),
+    }
+    match save_replay(state, &finished_game.replay) {
+        Ok(()) => {
+            let _ = lobby_broadcast.send(WsMessageToClient::ReplayAvailable(
+                finished_game.game_id.clone(),
+            ));
         }
+        Err(e) => println!(
+            "failed to save replay of game {} : {:?}",
+            finished_game.game_id, e
+        ),
     }
 }
 
-enum OutcomeAssault {
-    AttackingSameTile, // happens typically on side of the board, when going into a wall
-    BlockedByMountain,
-    NotEnoughTroops,
-    TileNotOwned,
-    SelfTroopsMove,
-    ConquerEmpty,
-    Tie,
-    Victory(String, usize), // loser_uuid, how many invaders survive on defensive case
-    Defeat(usize),          // how many loss on defensive case (1 remaining on attacking)
-    VictoryCastle(usize),
-}
-
-fn resolve_assault(
-    real_attacker_uuid: String,
-    board: &[Vec<Tile>],
-    attacker_xy: (usize, usize),
-    defender_xy: (usize, usize),
-) -> OutcomeAssault {
-    if attacker_xy.0 == defender_xy.0 && attacker_xy.1 == defender_xy.1 {
-        return OutcomeAssault::AttackingSameTile;
+// Colors and spawns are dealt in the order of the players (uuid, name, is_bot),
+// in team mode the players are dealt to the teams in turn. Nobody is placed when
+// some players would be left without a spawn.
+pub fn place_players(
+    engine: &mut GameEngine,
+    spawns: &[(usize, usize)],
+    players: &[(String, String, bool)],
+    nb_teams: Option<usize>,
+) -> Result<(), String> {
+    if spawns.len() < players.len() {
+        return Err(format!(
+            "{} spawns for {} players",
+            spawns.len(),
+            players.len()
+        ));
     }
-    let attacking_board = &board[attacker_xy.0][attacker_xy.1];
-    let defending_board = &board[defender_xy.0][defender_xy.1];
-    let nb_attacking_troops = attacking_board.nb_troops.saturating_sub(1); // 1 troop must remain on the current case
-    let nb_defending_troops = defending_board.nb_troops;
-    if nb_attacking_troops == 0 {
-        return OutcomeAssault::NotEnoughTroops;
+    let mut unavailable_colors = vec![];
+    for (index, ((player_uuid, player_name, is_bot), xy)) in players.iter().zip(spawns).enumerate()
+    {
+        let new_player_color = Color::pick_available_color(&unavailable_colors)
+            .expect("no player color available")
+            .clone();
+        unavailable_colors.push(new_player_color.clone());
+        engine.add_player(
+            player_uuid.clone(),
+            player_name.clone(),
+            new_player_color,
+            *xy,
+            nb_teams.map(|nb_teams| index % nb_teams),
+            *is_bot,
+        );
     }
-    if defending_board.tile_type == TileType::Mountain {
-        return OutcomeAssault::BlockedByMountain;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        configs::app_state::Tile,
+        service_layer::player_service::{Direction, PlayerMove},
+    };
+
+    // What the clients do with a delta
+    fn apply_delta(board: &mut [Vec<TileUpdate>], delta: Vec<TileDelta>) {
+        for TileDelta(x, y, tile) in delta {
+            board[x][y] = tile;
+        }
     }
-    // If a position move in the queue was stolen, the player_uuid of the attacking
-    // tile will be different from the real attacker, and the attack forbidden
-    match attacking_board.player_uuid.clone() {
-        None => return OutcomeAssault::TileNotOwned,
-        Some(attacker_uuid) => {
-            if attacker_uuid != real_attacker_uuid {
-                return OutcomeAssault::TileNotOwned;
-            }
+
+    #[test]
+    fn deltas_rebuild_the_board_of_every_tick() {
+        let mut engine = GameEngine::new(vec![vec![Tile::default(); 8]; 8]);
+        let players = [
+            ("a".to_string(), "alice".to_string(), false),
+            ("b".to_string(), "bob".to_string(), false),
+        ];
+        place_players(&mut engine, &[(0, 0), (7, 7)], &players, None).unwrap();
+        let inputs = TickInputs {
+            active_players: engine.players.keys().cloned().collect(),
+        };
+        for _ in 0..3 {
+            engine.step(&inputs);
+        }
+        for direction in [Direction::Right, Direction::Right, Direction::Down] {
+            engine.queue_move(
+                "a",
+                PlayerMove {
+                    direction,
+                    half: false,
+                    from: None,
+                },
+            );
+        }
+        let mut client_board = engine.fog_of_war_view("a");
+        for _ in 0..12 {
+            engine.step(&inputs);
+            let board = engine.fog_of_war_view("a");
+            let delta = board_delta(&client_board, &board);
+            assert!(!delta.is_empty()); // the kingdom grows every tick
+            apply_delta(&mut client_board, delta);
+            assert!(client_board == board);
         }
     }
-    if attacking_board.player_uuid == defending_board.player_uuid {
-        return OutcomeAssault::SelfTroopsMove;
+
+    #[test]
+    fn unchanged_board_has_an_empty_delta() {
+        let engine = GameEngine::new(vec![vec![Tile::default(); 8]; 8]);
+        let board = engine.full_view();
+        assert!(board_delta(&board, &board).is_empty());
     }
 
-    match defending_board.status {
-        TileStatus::Occupied => match nb_attacking_troops.cmp(&nb_defending_troops) {
-            Ordering::Equal => OutcomeAssault::Tie,
-            Ordering::Greater => OutcomeAssault::Victory(
-                defending_board
-                    .player_uuid
-                    .clone()
-                    .expect("no defender name on attacked tile"),
-                nb_attacking_troops - nb_defending_troops,
-            ),
-            Ordering::Less => OutcomeAssault::Defeat(nb_defending_troops - nb_attacking_troops),
-        },
-        TileStatus::Empty if defending_board.tile_type == TileType::Castle => {
-            match nb_attacking_troops.cmp(&nb_defending_troops) {
-                Ordering::Equal => OutcomeAssault::Tie,
-                Ordering::Greater => {
-                    OutcomeAssault::VictoryCastle(nb_attacking_troops - nb_defending_troops)
-                }
-                Ordering::Less => OutcomeAssault::Defeat(nb_defending_troops - nb_attacking_troops),
-            }
-        }
-        TileStatus::Empty => OutcomeAssault::ConquerEmpty,
+    #[test]
+    fn nobody_is_placed_without_a_spawn_for_everyone() {
+        let mut engine = GameEngine::new(vec![vec![Tile::default(); 8]; 8]);
+        let players = [
+            ("a".to_string(), "alice".to_string(), false),
+            ("b".to_string(), "bob".to_string(), false),
+        ];
+        assert!(place_players(&mut engine, &[(0, 0)], &players, None).is_err());
+        assert!(engine.players.is_empty());
+        assert!(engine.board[0][0].player_uuid.is_none());
     }
 }
@@ -0,0 +1,146 @@
+use crate::configs::app_state::AppState;
+use crate::constants::{
+    LEADERBOARD_MIN_GAMES_FOR_WIN_RATE, LEADERBOARD_REFRESH_INTERVAL_SEC, LEADERBOARD_SIZE,
+};
+use crate::custom_errors::service_errors::ServiceError;
+use crate::data_access_layer::leaderboard_dal;
+use crate::requests::requests::{
+    GameMode, LeaderboardEntry, LeaderboardPeriod, LeaderboardRequest, LeaderboardResponse,
+    LeaderboardSort,
+};
+use crate::utilities::responses::{response_ok, ApiResponse};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Datelike, Days, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::time::{interval, Duration};
+
+// (period, mode or all of them, lobby capacity or all of them, sorted by)
+type LeaderboardKey = (
+    LeaderboardPeriod,
+    Option<GameMode>,
+    Option<usize>,
+    LeaderboardSort,
+);
+
+// Leaderboards are served from this snapshot, recomputed every LEADERBOARD_REFRESH_INTERVAL_SEC
+#[derive(Debug, Default)]
+pub struct LeaderboardSnapshot {
+    pub refreshed_at: i64, // unix timestamp seconds
+    pub leaderboards: HashMap<LeaderboardKey, Vec<LeaderboardEntry>>,
+}
+
+pub async fn leaderboard_loop(state: Arc<AppState>) {
+    let mut interval = interval(Duration::from_secs(LEADERBOARD_REFRESH_INTERVAL_SEC));
+    loop {
+        interval.tick().await; // The first tick completes immediately
+        match compute_snapshot(&state) {
+            Ok(snapshot) => {
+                *state
+                    .leaderboards
+                    .write()
+                    .expect("failed to lock leaderboards") = snapshot
+            }
+            Err(e) => println!("failed to refresh leaderboards : {:?}", e),
+        }
+    }
+}
+
+fn compute_snapshot(state: &Arc<AppState>) -> Result<LeaderboardSnapshot, ServiceError> {
+    let now = Utc::now();
+    // the modes and capacities of the lobbies, each of them on its own and across the others
+    let mut filters = BTreeSet::new();
+    for lobby in state.lobbies.iter() {
+        let lobby = lobby.read().expect("failed to lock lobby");
+        for mode in [Some(lobby.mode()), None] {
+            for player_capacity in [Some(lobby.player_capacity), None] {
+                filters.insert((mode, player_capacity));
+            }
+        }
+    }
+
+    let mut leaderboards = HashMap::new();
+    for period in [
+        LeaderboardPeriod::AllTime,
+        LeaderboardPeriod::Monthly,
+        LeaderboardPeriod::Weekly,
+    ] {
+        for (mode, player_capacity) in filters.iter() {
+            for sort_by in [
+                LeaderboardSort::Rating,
+                LeaderboardSort::Wins,
+                LeaderboardSort::WinRate,
+                LeaderboardSort::GamesPlayed,
+            ] {
+                let min_games = match sort_by {
+                    LeaderboardSort::WinRate => LEADERBOARD_MIN_GAMES_FOR_WIN_RATE,
+                    _ => 1,
+                };
+                let entries = leaderboard_dal::get_leaderboard(
+                    state,
+                    period_start(period, now),
+                    *mode,
+                    *player_capacity,
+                    sort_by,
+                    min_games,
+                    LEADERBOARD_SIZE,
+                )?;
+                leaderboards.insert((period, *mode, *player_capacity, sort_by), entries);
+            }
+        }
+    }
+
+    Ok(LeaderboardSnapshot {
+        refreshed_at: now.timestamp(),
+        leaderboards,
+    })
+}
+
+fn period_start(period: LeaderboardPeriod, now: DateTime<Utc>) -> i64 {
+    let today = now.date_naive();
+    let first_day = match period {
+        LeaderboardPeriod::AllTime => return 0,
+        LeaderboardPeriod::Monthly => today.with_day(1),
+        LeaderboardPeriod::Weekly => {
+            today.checked_sub_days(Days::new(today.weekday().num_days_from_monday() as u64))
+        }
+    };
+    first_day
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .expect("failed to compute leaderboard period start")
+        .and_utc()
+        .timestamp()
+}
+
+pub async fn get_leaderboard(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<LeaderboardRequest>,
+) -> Result<(StatusCode, Json<ApiResponse<LeaderboardResponse>>), ServiceError> {
+    let snapshot = state
+        .leaderboards
+        .read()
+        .expect("failed to lock leaderboards");
+    let key = (
+        request.period.unwrap_or_default(),
+        request.mode,
+        request.player_capacity,
+        request.sort_by.unwrap_or_default(),
+    );
+    let entries = snapshot
+        .leaderboards
+        .get(&key)
+        .ok_or(ServiceError::NotFound)?
+        .iter()
+        .take(request.limit.unwrap_or(LEADERBOARD_SIZE))
+        .cloned()
+        .collect();
+
+    response_ok(Some(LeaderboardResponse {
+        refreshed_at: snapshot.refreshed_at,
+        entries,
+    }))
+}
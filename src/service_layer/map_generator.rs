@@ -0,0 +1,497 @@
+use crate::{
+    configs::app_state::{Tile, TileType},
+    constants::{
+        CASTLE_FAIRNESS_RADIUS, CASTLE_GARRISON, MAX_CASTLE_IMBALANCE, MAX_GAME_HEIGHT,
+        MAX_GAME_WIDTH, MAX_MAP_ATTEMPTS, MIN_GAME_HEIGHT, MIN_GAME_WIDTH, MIN_SPAWN_DISTANCE,
+        NB_CASTLES, NB_MOUNTAIN_RANGES, NB_MOUTAINS,
+    },
+    custom_errors::map_errors::MapError,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use super::player_service::Direction;
+
+#[derive(Debug, Clone)]
+pub struct GeneratedMap {
+    pub board: Vec<Vec<Tile>>,
+    pub spawns: Vec<(usize, usize)>, // kingdoms of the players, in the order they are dealt
+}
+
+// The same seed always gives the same map, none when it can't seat every player fairly
+pub trait MapGenerator {
+    fn generate(&self, seed: u64, nb_players: usize) -> Option<GeneratedMap>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum MapKind {
+    Uniform,        // mountains and castles scattered anywhere
+    MountainRanges, // mountains grouped in a few ranges
+    Symmetric,      // point symmetric board and spawns, made for 1v1
+}
+
+impl MapKind {
+    pub fn generator(&self) -> &'static dyn MapGenerator {
+        match self {
+            MapKind::Uniform => &UniformMap,
+            MapKind::MountainRanges => &MountainRangesMap,
+            MapKind::Symmetric => &SymmetricMap,
+        }
+    }
+}
+
+pub struct UniformMap;
+pub struct MountainRangesMap;
+pub struct SymmetricMap;
+
+impl MapGenerator for UniformMap {
+    fn generate(&self, seed: u64, nb_players: usize) -> Option<GeneratedMap> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut board = empty_board(&mut rng);
+        scatter(&mut board, &mut rng, NB_MOUTAINS, TileType::Mountain, 0);
+        scatter(
+            &mut board,
+            &mut rng,
+            NB_CASTLES,
+            TileType::Castle,
+            CASTLE_GARRISON,
+        );
+        let spawns = random_spawns(&board, nb_players, &mut spawn_rng(seed))?;
+        level_castles(&mut board, &spawns, |xy| xy);
+        Some(GeneratedMap { board, spawns })
+    }
+}
+
+impl MapGenerator for MountainRangesMap {
+    fn generate(&self, seed: u64, nb_players: usize) -> Option<GeneratedMap> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut board = empty_board(&mut rng);
+        let (width, height) = (board.len(), board[0].len());
+        // each range is a random walk
+        for _ in 0..NB_MOUNTAIN_RANGES {
+            let mut xy = (rng.gen_range(0..width), rng.gen_range(0..height));
+            for _ in 0..NB_MOUTAINS / NB_MOUNTAIN_RANGES {
+                board[xy.0][xy.1].tile_type = TileType::Mountain;
+                let direction = DIRECTIONS[rng.gen_range(0..DIRECTIONS.len())];
+                xy = direction.destination(xy, width, height);
+            }
+        }
+        scatter(
+            &mut board,
+            &mut rng,
+            NB_CASTLES,
+            TileType::Castle,
+            CASTLE_GARRISON,
+        );
+        let spawns = random_spawns(&board, nb_players, &mut spawn_rng(seed))?;
+        level_castles(&mut board, &spawns, |xy| xy);
+        Some(GeneratedMap { board, spawns })
+    }
+}
+
+impl MapGenerator for SymmetricMap {
+    fn generate(&self, seed: u64, nb_players: usize) -> Option<GeneratedMap> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut board = empty_board(&mut rng);
+        let (width, height) = (board.len(), board[0].len());
+        let mirror = |(x, y): (usize, usize)| (width - 1 - x, height - 1 - y);
+        for (count, tile_type, nb_troops) in [
+            (NB_MOUTAINS / 2, TileType::Mountain, 0),
+            (NB_CASTLES / 2, TileType::Castle, CASTLE_GARRISON),
+        ] {
+            for _ in 0..count {
+                let xy = (rng.gen_range(0..width), rng.gen_range(0..height));
+                if board[xy.0][xy.1].tile_type == TileType::Blank {
+                    for (x, y) in [xy, mirror(xy)] {
+                        board[x][y].tile_type = tile_type.clone();
+                        board[x][y].nb_troops = nb_troops;
+                    }
+                }
+            }
+        }
+        // spawns go by mirrored pairs, an odd player out gets a tile of its own
+        let tiles = shuffled_blank_tiles(&board, &mut spawn_rng(seed));
+        let mut spread = SpawnSpread::default();
+        while spread.spawns.len() + 1 < nb_players {
+            let xy = tiles.iter().copied().find(|&xy| {
+                xy != mirror(xy)
+                    && spread.fits(xy)
+                    && spread.fits(mirror(xy))
+                    && walking_distances(&board, xy)[mirror(xy).0][mirror(xy).1]
+                        .is_some_and(|distance| distance >= MIN_SPAWN_DISTANCE)
+            })?;
+            spread.add(&board, xy);
+            spread.add(&board, mirror(xy));
+        }
+        if spread.spawns.len() < nb_players {
+            let xy = tiles.iter().copied().find(|&xy| spread.fits(xy))?;
+            spread.add(&board, xy);
+        }
+        level_castles(&mut board, &spread.spawns, mirror);
+        Some(GeneratedMap {
+            board,
+            spawns: spread.spawns,
+        })
+    }
+}
+
+// Draws maps until a fair one comes up, starting with the seed itself. The generators
+// seldom miss, after MAX_MAP_ATTEMPTS misses the players get an open board, fair as well.
+pub fn generate_map(kind: MapKind, seed: u64, nb_players: usize) -> GeneratedMap {
+    let generator = kind.generator();
+    let mut attempt_seeds = StdRng::seed_from_u64(seed);
+    let mut attempt_seed = seed;
+    for _ in 0..MAX_MAP_ATTEMPTS {
+        if let Some(map) = generator
+            .generate(attempt_seed, nb_players)
+            .filter(|map| validate_map(&map.board, &map.spawns).is_ok())
+        {
+            return map;
+        }
+        attempt_seed = attempt_seeds.gen();
+    }
+    open_map(nb_players)
+}
+
+// The largest board without any mountain or castle, spawns as far from each other as they go
+fn open_map(nb_players: usize) -> GeneratedMap {
+    let board = vec![vec![Tile::default(); MAX_GAME_HEIGHT]; MAX_GAME_WIDTH];
+    let mut spread = SpawnSpread::default();
+    spread.add(&board, (0, 0));
+    while spread.spawns.len() < nb_players {
+        let farthest = board
+            .iter()
+            .enumerate()
+            .flat_map(|(x, column)| (0..column.len()).map(move |y| (x, y)))
+            .max_by_key(|&(x, y)| spread.nearest[x][y])
+            .expect("empty open board");
+        spread.add(&board, farthest);
+    }
+    GeneratedMap {
+        board,
+        spawns: spread.spawns,
+    }
+}
+
+// Every spawn can reach the others, far enough from them, with about as many castles nearby
+pub fn validate_map(board: &[Vec<Tile>], spawns: &[(usize, usize)]) -> Result<(), MapError> {
+    for (i, &(x, y)) in spawns.iter().enumerate() {
+        if x >= board.len()
+            || y >= board[0].len()
+            || board[x][y].tile_type != TileType::Blank
+            || spawns[..i].contains(&(x, y))
+        {
+            return Err(MapError::InvalidSpawn);
+        }
+    }
+    let mut nearby_castles = vec![];
+    for spawn in spawns.iter() {
+        let distances = walking_distances(board, *spawn);
+        for other in spawns.iter().filter(|other| *other != spawn) {
+            match distances[other.0][other.1] {
+                None => return Err(MapError::SpawnUnreachable),
+                Some(distance) if distance < MIN_SPAWN_DISTANCE => {
+                    return Err(MapError::SpawnsTooClose)
+                }
+                _ => (),
+            }
+        }
+        nearby_castles.push(
+            board
+                .iter()
+                .flatten()
+                .zip(distances.iter().flatten())
+                .filter(|(tile, distance)| {
+                    tile.tile_type == TileType::Castle
+                        && distance.is_some_and(|distance| distance <= CASTLE_FAIRNESS_RADIUS)
+                })
+                .count(),
+        );
+    }
+    let most = nearby_castles.iter().max().unwrap_or(&0);
+    let fewest = nearby_castles.iter().min().unwrap_or(&0);
+    if most - fewest > MAX_CASTLE_IMBALANCE {
+        return Err(MapError::UnfairCastles);
+    }
+    Ok(())
+}
+
+// Number of moves to reach each tile from `from`, none behind mountains
+fn walking_distances(board: &[Vec<Tile>], from: (usize, usize)) -> Vec<Vec<Option<usize>>> {
+    let (width, height) = (board.len(), board[0].len());
+    let mut distances = vec![vec![None; height]; width];
+    distances[from.0][from.1] = Some(0);
+    let mut to_visit = VecDeque::from([from]);
+    while let Some(xy) = to_visit.pop_front() {
+        let distance = distances[xy.0][xy.1].expect("visited tile without distance");
+        for direction in DIRECTIONS {
+            let (x, y) = direction.destination(xy, width, height);
+            if distances[x][y].is_none() && board[x][y].tile_type != TileType::Mountain {
+                distances[x][y] = Some(distance + 1);
+                to_visit.push_back((x, y));
+            }
+        }
+    }
+    distances
+}
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Left,
+    Direction::Right,
+    Direction::Up,
+    Direction::Down,
+];
+
+fn empty_board(rng: &mut StdRng) -> Vec<Vec<Tile>> {
+    let width = rng.gen_range(MIN_GAME_WIDTH..MAX_GAME_WIDTH);
+    let height = rng.gen_range(MIN_GAME_HEIGHT..MAX_GAME_HEIGHT);
+    vec![vec![Tile::default(); height]; width]
+}
+
+// Tiles already taken are skipped, so a few less than `count` can be placed
+fn scatter(
+    board: &mut [Vec<Tile>],
+    rng: &mut StdRng,
+    count: usize,
+    tile_type: TileType,
+    nb_troops: usize,
+) {
+    let (width, height) = (board.len(), board[0].len());
+    for _ in 0..count {
+        let x = rng.gen_range(0..width);
+        let y = rng.gen_range(0..height);
+        if board[x][y].tile_type == TileType::Blank {
+            board[x][y].tile_type = tile_type.clone();
+            board[x][y].nb_troops = nb_troops;
+        }
+    }
+}
+
+fn random_spawns(
+    board: &[Vec<Tile>],
+    nb_players: usize,
+    rng: &mut StdRng,
+) -> Option<Vec<(usize, usize)>> {
+    let tiles = shuffled_blank_tiles(board, rng);
+    let mut spread = SpawnSpread::default();
+    while spread.spawns.len() < nb_players {
+        let xy = tiles.iter().copied().find(|&xy| spread.fits(xy))?;
+        spread.add(board, xy);
+    }
+    Some(spread.spawns)
+}
+
+fn shuffled_blank_tiles(board: &[Vec<Tile>], rng: &mut StdRng) -> Vec<(usize, usize)> {
+    let mut tiles: Vec<(usize, usize)> = board
+        .iter()
+        .enumerate()
+        .flat_map(|(x, column)| {
+            column
+                .iter()
+                .enumerate()
+                .filter(|(_, tile)| tile.tile_type == TileType::Blank)
+                .map(move |(y, _)| (x, y))
+        })
+        .collect();
+    tiles.shuffle(rng);
+    tiles
+}
+
+// Spawns placed so far, along with the walking distance from each tile to the nearest of them
+#[derive(Default)]
+struct SpawnSpread {
+    spawns: Vec<(usize, usize)>,
+    nearest: Vec<Vec<Option<usize>>>, // none out of reach of the spawns
+}
+
+impl SpawnSpread {
+    // Any tile can take the first spawn, the next ones must be reachable and far enough
+    fn fits(&self, (x, y): (usize, usize)) -> bool {
+        self.spawns.is_empty()
+            || self.nearest[x][y].is_some_and(|distance| distance >= MIN_SPAWN_DISTANCE)
+    }
+
+    fn add(&mut self, board: &[Vec<Tile>], xy: (usize, usize)) {
+        let distances = walking_distances(board, xy);
+        if self.spawns.is_empty() {
+            self.nearest = distances;
+        } else {
+            for (nearest, distance) in self
+                .nearest
+                .iter_mut()
+                .flatten()
+                .zip(distances.into_iter().flatten())
+            {
+                // the spawns all reach the same tiles, none stays none
+                *nearest = (*nearest).min(distance);
+            }
+        }
+        self.spawns.push(xy);
+    }
+}
+
+// Razes castles around the best provided spawn until the spawns are within MAX_CASTLE_IMBALANCE
+// of each other. On a symmetric board the mirror of a razed castle goes along with it.
+fn level_castles(
+    board: &mut [Vec<Tile>],
+    spawns: &[(usize, usize)],
+    mirror: impl Fn((usize, usize)) -> (usize, usize),
+) {
+    // castles don't block the way, razing them leaves the distances as they are
+    let distances: Vec<_> = spawns
+        .iter()
+        .map(|spawn| walking_distances(board, *spawn))
+        .collect();
+    let is_near = |spawn: usize, (x, y): (usize, usize)| {
+        distances[spawn][x][y].is_some_and(|distance| distance <= CASTLE_FAIRNESS_RADIUS)
+    };
+    loop {
+        let castles: Vec<(usize, usize)> = board
+            .iter()
+            .enumerate()
+            .flat_map(|(x, column)| {
+                column
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tile)| tile.tile_type == TileType::Castle)
+                    .map(move |(y, _)| (x, y))
+            })
+            .collect();
+        let nearby_castles: Vec<usize> = (0..spawns.len())
+            .map(|spawn| castles.iter().filter(|xy| is_near(spawn, **xy)).count())
+            .collect();
+        let (Some(richest), Some(poorest)) = (
+            (0..spawns.len()).max_by_key(|spawn| nearby_castles[*spawn]),
+            (0..spawns.len()).min_by_key(|spawn| nearby_castles[*spawn]),
+        ) else {
+            return;
+        };
+        if nearby_castles[richest] - nearby_castles[poorest] <= MAX_CASTLE_IMBALANCE {
+            return;
+        }
+        // the richest spawn has more castles around than the poorest, one of them is its own
+        let Some(&xy) = castles
+            .iter()
+            .find(|xy| is_near(richest, **xy) && !is_near(poorest, **xy))
+        else {
+            return;
+        };
+        for (x, y) in [xy, mirror(xy)] {
+            board[x][y] = Tile::default();
+        }
+    }
+}
+
+// Spawns use their own stream, so they don't replay the draws made by the board generation
+fn spawn_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed.wrapping_add(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank_board() -> Vec<Vec<Tile>> {
+        vec![vec![Tile::default(); MIN_GAME_HEIGHT]; MIN_GAME_WIDTH]
+    }
+
+    fn castle() -> Tile {
+        Tile {
+            tile_type: TileType::Castle,
+            nb_troops: CASTLE_GARRISON,
+            ..Tile::default()
+        }
+    }
+
+    #[test]
+    fn spawns_must_be_distinct_blank_tiles() {
+        let mut board = blank_board();
+        board[5][5].tile_type = TileType::Mountain;
+        board[10][10] = castle();
+        for spawns in [
+            vec![(0, 0), (MIN_GAME_WIDTH, 0)],
+            vec![(0, 0), (5, 5)],
+            vec![(0, 0), (10, 10)],
+            vec![(0, 0), (17, 17), (0, 0)],
+        ] {
+            assert_eq!(validate_map(&board, &spawns), Err(MapError::InvalidSpawn));
+        }
+        assert_eq!(validate_map(&board, &[(0, 0), (17, 17)]), Ok(()));
+    }
+
+    #[test]
+    fn spawns_must_reach_each_other_from_afar() {
+        let mut board = blank_board();
+        assert_eq!(
+            validate_map(&board, &[(0, 0), (MIN_SPAWN_DISTANCE - 1, 0)]),
+            Err(MapError::SpawnsTooClose)
+        );
+        assert_eq!(
+            validate_map(&board, &[(0, 0), (MIN_SPAWN_DISTANCE, 0)]),
+            Ok(())
+        );
+        for tile in board[MIN_GAME_WIDTH / 2].iter_mut() {
+            tile.tile_type = TileType::Mountain;
+        }
+        assert_eq!(
+            validate_map(&board, &[(0, 0), (17, 17)]),
+            Err(MapError::SpawnUnreachable)
+        );
+    }
+
+    #[test]
+    fn castles_must_be_shared_out_between_spawns() {
+        let mut board = blank_board();
+        board[1][0] = castle();
+        assert_eq!(validate_map(&board, &[(0, 0), (17, 17)]), Ok(()));
+        board[0][1] = castle();
+        assert_eq!(
+            validate_map(&board, &[(0, 0), (17, 17)]),
+            Err(MapError::UnfairCastles)
+        );
+        board[16][17] = castle();
+        assert_eq!(validate_map(&board, &[(0, 0), (17, 17)]), Ok(()));
+    }
+
+    #[test]
+    fn generated_maps_seat_every_player_fairly() {
+        for kind in [
+            MapKind::Uniform,
+            MapKind::MountainRanges,
+            MapKind::Symmetric,
+        ] {
+            for nb_players in 2..=6 {
+                for seed in 0..20 {
+                    let map = generate_map(kind, seed, nb_players);
+                    assert_eq!(map.spawns.len(), nb_players);
+                    assert_eq!(validate_map(&map.board, &map.spawns), Ok(()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn generated_maps_only_depend_on_their_seed() {
+        let map = generate_map(MapKind::MountainRanges, 42, 4);
+        let again = generate_map(MapKind::MountainRanges, 42, 4);
+        assert_eq!(map.spawns, again.spawns);
+        assert!(map
+            .board
+            .iter()
+            .flatten()
+            .zip(again.board.iter().flatten())
+            .all(|(tile, other)| tile.tile_type == other.tile_type
+                && tile.nb_troops == other.nb_troops));
+    }
+
+    #[test]
+    fn the_open_map_is_fair_as_well() {
+        for nb_players in 2..=8 {
+            let map = open_map(nb_players);
+            assert_eq!(map.spawns.len(), nb_players);
+            assert_eq!(validate_map(&map.board, &map.spawns), Ok(()));
+        }
+    }
+}
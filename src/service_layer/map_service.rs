@@ -0,0 +1,253 @@
+use crate::configs::app_state::{AppState, Tile, TileType};
+use crate::constants::{
+    CASTLE_GARRISON, DEFAULT_PAGE_SIZE, MAX_GAME_HEIGHT, MAX_GAME_WIDTH, MAX_MAP_NAME_LENGTH,
+    MAX_PAGE_SIZE, MIN_GAME_HEIGHT, MIN_GAME_WIDTH,
+};
+use crate::custom_errors::{map_errors::MapError, service_errors::ServiceError};
+use crate::data_access_layer::map_dal;
+use crate::requests::requests::{
+    CreateMapRequest, MapResponse, PaginatedResponse, PaginationRequest,
+};
+use crate::utilities::responses::{response_ok, ApiResponse};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::map_generator::{validate_map, GeneratedMap};
+
+// Hand-designed map, one string per row of the board, top to bottom :
+// '.' blank, '#' mountain, 'C' castle, 'S' spawn.
+// Spawns are dealt to the players in reading order.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CustomMap {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub rows: Vec<String>,
+    #[serde(default)]
+    pub garrisons: Vec<Garrison>, // castles not listed get CASTLE_GARRISON troops
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Garrison {
+    pub x: usize,
+    pub y: usize,
+    pub nb_troops: usize,
+}
+
+// Builds the board and the spawns, custom maps are held to the same rules as the generated ones
+pub fn load_map(map: &CustomMap) -> Result<GeneratedMap, MapError> {
+    if map.name.trim().is_empty() || map.name.chars().count() > MAX_MAP_NAME_LENGTH {
+        return Err(MapError::InvalidName);
+    }
+    if !(MIN_GAME_WIDTH..=MAX_GAME_WIDTH).contains(&map.width)
+        || !(MIN_GAME_HEIGHT..=MAX_GAME_HEIGHT).contains(&map.height)
+        || map.rows.len() != map.height
+        || map.rows.iter().any(|row| row.chars().count() != map.width)
+    {
+        return Err(MapError::InvalidDimensions);
+    }
+    let mut board = vec![vec![Tile::default(); map.height]; map.width];
+    let mut spawns = vec![];
+    for (y, row) in map.rows.iter().enumerate() {
+        for (x, tile) in row.chars().enumerate() {
+            match tile {
+                '.' => (),
+                '#' => board[x][y].tile_type = TileType::Mountain,
+                'C' => {
+                    board[x][y].tile_type = TileType::Castle;
+                    board[x][y].nb_troops = CASTLE_GARRISON;
+                }
+                'S' => spawns.push((x, y)),
+                _ => return Err(MapError::UnknownTile),
+            }
+        }
+    }
+    for garrison in map.garrisons.iter() {
+        match board
+            .get_mut(garrison.x)
+            .and_then(|column| column.get_mut(garrison.y))
+        {
+            Some(tile) if tile.tile_type == TileType::Castle => tile.nb_troops = garrison.nb_troops,
+            _ => return Err(MapError::InvalidGarrison),
+        }
+    }
+    if spawns.len() < 2 {
+        return Err(MapError::NotEnoughSpawns);
+    }
+    validate_map(&board, &spawns)?;
+    Ok(GeneratedMap { board, spawns })
+}
+
+// The map is checked and stored as uploaded
+pub async fn upload_map(
+    State(state): State<Arc<AppState>>,
+    Json(map): Json<CustomMap>,
+) -> Result<(StatusCode, Json<ApiResponse<MapResponse>>), ServiceError> {
+    let loaded = load_map(&map).map_err(ServiceError::InvalidMap)?;
+    let data = serde_json::to_vec(&map).map_err(|_| ServiceError::Internal)?;
+    let new_map = MapResponse {
+        map_id: Uuid::now_v7().to_string(),
+        name: map.name,
+        width: map.width,
+        height: map.height,
+        nb_spawns: loaded.spawns.len(),
+        created_at: Utc::now().timestamp(),
+    };
+    map_dal::create_map(
+        &state,
+        CreateMapRequest {
+            map_id: new_map.map_id.clone(),
+            name: new_map.name.clone(),
+            width: new_map.width,
+            height: new_map.height,
+            nb_spawns: new_map.nb_spawns,
+            created_at: new_map.created_at,
+            data,
+        },
+    )?;
+
+    response_ok(Some(new_map))
+}
+
+pub fn get_custom_map(state: &Arc<AppState>, map_id: String) -> Result<CustomMap, ServiceError> {
+    let data = map_dal::get_map_data(state, map_id)?;
+    serde_json::from_slice(&data).map_err(|_| ServiceError::Internal)
+}
+
+pub async fn get_map(
+    State(state): State<Arc<AppState>>,
+    Path(map_id): Path<String>,
+) -> Result<(StatusCode, Json<ApiResponse<CustomMap>>), ServiceError> {
+    response_ok(Some(get_custom_map(&state, map_id)?))
+}
+
+pub async fn get_maps(
+    State(state): State<Arc<AppState>>,
+    Query(pagination): Query<PaginationRequest>,
+) -> Result<
+    (
+        StatusCode,
+        Json<ApiResponse<PaginatedResponse<MapResponse>>>,
+    ),
+    ServiceError,
+> {
+    let page = pagination.page.unwrap_or(0);
+    let page_size = pagination
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    let maps = map_dal::get_maps(&state, page_size, page.saturating_mul(page_size))?;
+    let total_items = map_dal::count_maps(&state)?;
+
+    response_ok(Some(PaginatedResponse {
+        items: maps,
+        page,
+        page_size,
+        total_items,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two spawns in opposite corners, a castle next to each
+    fn custom_map() -> CustomMap {
+        let mut rows = vec![".".repeat(MIN_GAME_WIDTH); MIN_GAME_HEIGHT];
+        rows[0] = format!("SC{}", ".".repeat(MIN_GAME_WIDTH - 2));
+        rows[MIN_GAME_HEIGHT / 2] = "#".repeat(MIN_GAME_WIDTH - 1) + ".";
+        rows[MIN_GAME_HEIGHT - 1] = format!("{}CS", ".".repeat(MIN_GAME_WIDTH - 2));
+        CustomMap {
+            name: "corners".to_string(),
+            width: MIN_GAME_WIDTH,
+            height: MIN_GAME_HEIGHT,
+            rows,
+            garrisons: vec![Garrison {
+                x: 1,
+                y: 0,
+                nb_troops: 20,
+            }],
+        }
+    }
+
+    #[test]
+    fn a_valid_map_loads() {
+        let loaded = load_map(&custom_map()).unwrap();
+        assert_eq!(
+            loaded.spawns,
+            vec![(0, 0), (MIN_GAME_WIDTH - 1, MIN_GAME_HEIGHT - 1)]
+        );
+        assert_eq!(loaded.board.len(), MIN_GAME_WIDTH);
+        assert_eq!(loaded.board[1][0].tile_type, TileType::Castle);
+        assert_eq!(loaded.board[1][0].nb_troops, 20);
+        let last = MIN_GAME_WIDTH - 2;
+        assert_eq!(
+            loaded.board[last][MIN_GAME_HEIGHT - 1].nb_troops,
+            CASTLE_GARRISON
+        );
+        assert_eq!(
+            loaded.board[0][MIN_GAME_HEIGHT / 2].tile_type,
+            TileType::Mountain
+        );
+    }
+
+    #[test]
+    fn maps_need_a_name() {
+        for name in ["", "   ", &"x".repeat(MAX_MAP_NAME_LENGTH + 1)] {
+            let map = CustomMap {
+                name: name.to_string(),
+                ..custom_map()
+            };
+            assert_eq!(load_map(&map).unwrap_err(), MapError::InvalidName);
+        }
+    }
+
+    #[test]
+    fn rows_must_match_the_dimensions() {
+        let mut too_small = custom_map();
+        too_small.height = MIN_GAME_HEIGHT - 1;
+        too_small.rows.pop();
+        let mut missing_row = custom_map();
+        missing_row.rows.pop();
+        let mut short_row = custom_map();
+        short_row.rows[3].pop();
+        for map in [too_small, missing_row, short_row] {
+            assert_eq!(load_map(&map).unwrap_err(), MapError::InvalidDimensions);
+        }
+    }
+
+    #[test]
+    fn unknown_tiles_are_rejected() {
+        let mut map = custom_map();
+        map.rows[3].replace_range(0..1, "K");
+        assert_eq!(load_map(&map).unwrap_err(), MapError::UnknownTile);
+    }
+
+    #[test]
+    fn garrisons_must_be_on_castles() {
+        for (x, y) in [(0, 0), (2, 0), (MIN_GAME_WIDTH, 0)] {
+            let mut map = custom_map();
+            map.garrisons[0].x = x;
+            map.garrisons[0].y = y;
+            assert_eq!(load_map(&map).unwrap_err(), MapError::InvalidGarrison);
+        }
+    }
+
+    #[test]
+    fn maps_need_two_fair_spawns() {
+        let mut map = custom_map();
+        map.rows[0].replace_range(0..1, ".");
+        assert_eq!(load_map(&map).unwrap_err(), MapError::NotEnoughSpawns);
+        let mut map = custom_map();
+        map.rows[MIN_GAME_HEIGHT / 2].replace_range(MIN_GAME_WIDTH - 1.., "#");
+        assert_eq!(load_map(&map).unwrap_err(), MapError::SpawnUnreachable);
+    }
+}
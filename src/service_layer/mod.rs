@@ -1,3 +1,13 @@
+pub mod bot_account_service;
+pub mod bot_service;
+pub mod game_engine;
+pub mod game_history_service;
 pub mod game_service;
+pub mod leaderboard_service;
+pub mod map_generator;
+pub mod map_service;
 pub mod player_service;
+pub mod rating_service;
+pub mod replay_service;
+pub mod schema_service;
 pub mod websocket_service;
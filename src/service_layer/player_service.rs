@@ -8,6 +8,7 @@ use crate::requests::requests::{
     CreatePlayerRequest, IsValidPlayernameRequest, IsValidPlayernameResponse,
     RequestNewPlayerResponse, UpdateNameRequest,
 };
+use crate::service_layer::bot_service::is_bot_name;
 use crate::utilities::responses::{response_ok, ApiResponse};
 use axum::{
     extract::{Path, State},
@@ -16,12 +17,12 @@ use axum::{
 };
 use rand::seq::SliceRandom;
 use rand::Rng;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::fmt::Debug;
 use std::sync::Arc;
-use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 
 #[derive(Debug)]
@@ -30,26 +31,33 @@ pub struct Player {
     pub name: String,
     pub personal_tx: mpsc::UnboundedSender<WsMessageToClient>,
     pub playing_in_lobby: Option<usize>,
-    pub queued_moves: VecDeque<PlayerMove>,
-    pub xy: (usize, usize),
-    pub color: Color,
+    pub is_bot: bool, // bot account, connected through the bot api
 }
 
-#[derive(Debug, Clone, Copy, Serialize)]
-pub enum PlayerMove {
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub enum Direction {
     Left,
     Right,
     Up,
     Down,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+pub struct PlayerMove {
+    pub direction: Direction,
+    #[serde(default)]
+    pub half: bool, // only send half of the troops, the other half stays behind
+    #[serde(default)]
+    pub from: Option<(usize, usize)>, // explicit origin, the move is chained from the cursor otherwise
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct PlayerMoves {
     pub queued_moves: VecDeque<PlayerMove>,
     pub xy: (usize, usize),
 }
 
-#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 pub enum Color {
     Grey, // Reserved for inactives
     Red,
@@ -57,16 +65,18 @@ pub enum Color {
     Pink,
     Green,
     Yellow,
+    Orange,
 }
 
 impl Color {
     pub fn pick_available_color(unavailable_colors: &[Color]) -> Option<&Color> {
-        const COLORS: [Color; 5] = [
+        const COLORS: [Color; 6] = [
             Color::Red,
             Color::Blue,
             Color::Pink,
             Color::Green,
             Color::Yellow,
+            Color::Orange,
         ];
         COLORS
             .iter()
@@ -74,19 +84,74 @@ impl Color {
     }
 }
 
-impl std::str::FromStr for PlayerMove {
+impl Direction {
+    // Tile reached when moving from `from`, a move into a wall stays on the same tile
+    pub fn destination(&self, from: (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Direction::Left => (from.0.saturating_sub(1), from.1),
+            Direction::Right => ((from.0 + 1).min(width - 1), from.1),
+            Direction::Up => (from.0, from.1.saturating_sub(1)),
+            Direction::Down => (from.0, (from.1 + 1).min(height - 1)),
+        }
+    }
+}
+
+impl PlayerMove {
+    pub fn destination(&self, from: (usize, usize), width: usize, height: usize) -> (usize, usize) {
+        self.direction.destination(from, width, height)
+    }
+
+    // At least 1 troop always stays on the tile the move starts from
+    pub fn nb_moving_troops(&self, nb_troops: usize) -> usize {
+        if self.half {
+            nb_troops / 2
+        } else {
+            nb_troops.saturating_sub(1)
+        }
+    }
+}
+
+impl std::str::FromStr for Direction {
     type Err = ();
-    fn from_str(input: &str) -> Result<PlayerMove, Self::Err> {
+    fn from_str(input: &str) -> Result<Direction, Self::Err> {
         match input {
-            "left" => Ok(PlayerMove::Left),
-            "right" => Ok(PlayerMove::Right),
-            "up" => Ok(PlayerMove::Up),
-            "down" => Ok(PlayerMove::Down),
+            "left" => Ok(Direction::Left),
+            "right" => Ok(Direction::Right),
+            "up" => Ok(Direction::Up),
+            "down" => Ok(Direction::Down),
             _ => Err(()),
         }
     }
 }
 
+// "left" moves all the troops but one, "left half" only half of them,
+// "left 3 4" or "left half 3 4" start from the tile (3, 4) instead of the cursor
+impl std::str::FromStr for PlayerMove {
+    type Err = ();
+    fn from_str(input: &str) -> Result<PlayerMove, Self::Err> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let (direction, half, origin) = match words[..] {
+            [direction] => (direction, false, None),
+            [direction, "half"] => (direction, true, None),
+            [direction, x, y] => (direction, false, Some((x, y))),
+            [direction, "half", x, y] => (direction, true, Some((x, y))),
+            _ => return Err(()),
+        };
+        let from = match origin {
+            Some((x, y)) => Some((
+                x.parse::<usize>().map_err(|_| ())?,
+                y.parse::<usize>().map_err(|_| ())?,
+            )),
+            None => None,
+        };
+        Ok(PlayerMove {
+            direction: direction.parse::<Direction>()?,
+            half,
+            from,
+        })
+    }
+}
+
 pub async fn request_new_player(
     State(state): State<Arc<AppState>>,
 ) -> Result<(StatusCode, Json<ApiResponse<RequestNewPlayerResponse>>), ServiceError> {
@@ -118,7 +183,7 @@ pub async fn is_valid_playername(
     response_ok(Some(is_valid))
 }
 
-fn internal_is_valid_playername(
+pub fn internal_is_valid_playername(
     player_name: String,
     state: &Arc<AppState>,
 ) -> Result<IsValidPlayernameResponse, ServiceError> {
@@ -135,18 +200,24 @@ fn internal_is_valid_playername(
                 )),
             })
         }
-        Ordering::Greater => match name_length.cmp(&MAXIMUM_PLAYERNAME_LENGTH) {
-            Ordering::Greater => {
+        Ordering::Greater => {
+            if name_length > MAXIMUM_PLAYERNAME_LENGTH {
                 return Ok(IsValidPlayernameResponse {
                     is_valid: false,
                     reason: Some(format!(
                         "player name is too long ({} characters), it should be at most {}",
                         name_length, MAXIMUM_PLAYERNAME_LENGTH
                     )),
-                })
+                });
             }
-            _ => (),
-        },
+        }
+    }
+
+    if is_bot_name(&player_name) {
+        return Ok(IsValidPlayernameResponse {
+            is_valid: false,
+            reason: Some("player name is reserved for the bots".to_string()),
+        });
     }
 
     match data_access_layer::player_dal::get_player_by_name(state, player_name.clone()) {
@@ -0,0 +1,139 @@
+use crate::configs::app_state::AppState;
+use crate::constants::{INITIAL_RATING, RATING_K_FACTOR};
+use crate::custom_errors::service_errors::ServiceError;
+use crate::custom_errors::sqlite_errors::SqliteError;
+use crate::data_access_layer::rating_dal;
+use crate::models::messages_to_clients::RatingChange;
+use crate::requests::requests::SaveRatingRequest;
+use crate::service_layer::game_history_service::FinishedGame;
+use std::sync::Arc;
+
+// Elo generalised to free for all : every player plays a virtual duel against each
+// of the others, won if he placed better, drawn on the same placement. The K factor
+// is shared between the duels so that a 5 players game weights as much as a 1v1.
+pub fn elo_deltas(players: &[(usize, i64)]) -> Vec<i64> {
+    // (placement, rating)
+    if players.len() < 2 {
+        return vec![0; players.len()];
+    }
+    let k_per_duel = RATING_K_FACTOR / (players.len() - 1) as f64;
+    players
+        .iter()
+        .enumerate()
+        .map(|(i, (placement, rating))| {
+            let delta: f64 = players
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (opponent_placement, opponent_rating))| {
+                    let expected =
+                        1.0 / (1.0 + 10f64.powf((opponent_rating - rating) as f64 / 400.0));
+                    let score = match placement.cmp(opponent_placement) {
+                        std::cmp::Ordering::Less => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Greater => 0.0,
+                    };
+                    k_per_duel * (score - expected)
+                })
+                .sum();
+            delta.round() as i64
+        })
+        .collect()
+}
+
+pub fn get_rating(state: &Arc<AppState>, player_uuid: String) -> Result<i64, ServiceError> {
+    match rating_dal::get_rating(state, player_uuid) {
+        Ok(rating) => Ok(rating),
+        Err(SqliteError::NotFound) => Ok(INITIAL_RATING),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// To be called at the end of a ranked game, once the game history is saved
+pub fn update_ratings(
+    state: &Arc<AppState>,
+    game: &FinishedGame,
+) -> Result<Vec<RatingChange>, ServiceError> {
+    // bots are ignored, the players are rated on their placement among humans
+    let ranking: Vec<_> = game
+        .engine
+        .ranking()
+        .into_iter()
+        .filter(|player| !player.is_bot)
+        .collect();
+    let mut players = vec![];
+    for player in ranking.iter() {
+        players.push((
+            player.placement,
+            get_rating(state, player.player_uuid.clone())?,
+        ));
+    }
+    let deltas = elo_deltas(&players);
+
+    let requests: Vec<SaveRatingRequest> = ranking
+        .iter()
+        .zip(players.iter().zip(deltas.iter()))
+        .map(|(player, ((_, rating), delta))| SaveRatingRequest {
+            player_uuid: player.player_uuid.clone(),
+            rating_before: *rating,
+            rating_after: rating + delta,
+        })
+        .collect();
+    rating_dal::save_ratings(state, game.game_id.clone(), &requests)?;
+
+    Ok(ranking
+        .into_iter()
+        .zip(requests)
+        .map(|(player, request)| RatingChange {
+            player_name: player.name,
+            rating_before: request.rating_before,
+            rating_after: request.rating_after,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn even_duel_moves_half_the_k_factor() {
+        let half_k = (RATING_K_FACTOR / 2.0).round() as i64;
+        assert_eq!(
+            elo_deltas(&[(1, INITIAL_RATING), (2, INITIAL_RATING)]),
+            vec![half_k, -half_k]
+        );
+    }
+
+    #[test]
+    fn draws_between_equals_change_nothing() {
+        assert_eq!(
+            elo_deltas(&[(1, 1500), (1, 1500), (1, 1500)]),
+            vec![0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn upsets_are_worth_more() {
+        let expected_win = elo_deltas(&[(1, 1600), (2, 1200)]);
+        let upset = elo_deltas(&[(2, 1600), (1, 1200)]);
+        assert!(upset[1] > expected_win[0]);
+        assert!(upset[0] < expected_win[1]);
+    }
+
+    #[test]
+    fn free_for_all_weights_as_much_as_a_duel() {
+        let deltas = elo_deltas(&[(1, 1200), (2, 1200), (2, 1200), (4, 1200), (5, 1200)]);
+        // winning against everyone at the same rating is worth a won duel
+        assert_eq!(deltas[0], (RATING_K_FACTOR / 2.0).round() as i64);
+        assert_eq!(deltas[1], deltas[2]);
+        assert_eq!(deltas[4], -deltas[0]);
+        assert!(deltas.iter().sum::<i64>().abs() <= 1);
+    }
+
+    #[test]
+    fn a_lone_player_is_not_rated() {
+        assert_eq!(elo_deltas(&[(1, 1200)]), vec![0]);
+        assert_eq!(elo_deltas(&[]), Vec::<i64>::new());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::configs::app_state::{AppState, Tile, TileType};
+use crate::custom_errors::service_errors::ServiceError;
+use crate::data_access_layer::replay_dal;
+use crate::requests::requests::ReplayTickResponse;
+use crate::utilities::responses::{response_ok, ApiResponse};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use super::{
+    game_engine::{GameEngine, TickInputs, TickOutcome},
+    player_service::{Color, Direction, PlayerMove},
+};
+
+// Everything needed to re-simulate a game : the map before the kingdoms were placed,
+// the roster and, for each tick, who was connected and which moves were played.
+// Players are referred to by their index in `players`, uuids never leave the server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Replay {
+    pub game_id: String,
+    pub lobby_id: usize,
+    pub seed: u64,
+    pub initial_board: Vec<Vec<ReplayTile>>,
+    pub players: Vec<ReplayPlayer>,
+    pub ticks: Vec<ReplayTick>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayTile(pub TileType, pub usize); // tile type, nb troops
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayPlayer {
+    pub name: String,
+    pub color: Color,
+    pub kingdom_xy: (usize, usize),
+    #[serde(default)]
+    pub team: Option<usize>,
+    #[serde(default)]
+    pub is_bot: bool,
+}
+
+// Tuples rather than named fields, a game is made of hundreds of ticks
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayTick(pub Vec<usize>, pub Vec<ReplayMove>, pub Vec<usize>); // active players, played moves, surrenders
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayMove(
+    pub usize,
+    pub (usize, usize),
+    pub Direction,
+    #[serde(default)] pub bool,
+); // player, from, direction, half
+
+impl Replay {
+    pub fn new(game_id: String, lobby_id: usize, seed: u64, board: &[Vec<Tile>]) -> Self {
+        Replay {
+            game_id,
+            lobby_id,
+            seed,
+            initial_board: board
+                .iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .map(|tile| ReplayTile(tile.tile_type.clone(), tile.nb_troops))
+                        .collect()
+                })
+                .collect(),
+            players: vec![],
+            ticks: vec![],
+        }
+    }
+
+    // To be called once the kingdoms are placed, the engine order gives the player indexes
+    pub fn record_roster(&mut self, engine: &GameEngine) {
+        self.players = engine
+            .players
+            .values()
+            .map(|player| ReplayPlayer {
+                name: player.name.clone(),
+                color: player.color.clone(),
+                kingdom_xy: player.xy,
+                team: player.team,
+                is_bot: player.is_bot,
+            })
+            .collect();
+    }
+
+    pub fn record_tick(&mut self, engine: &GameEngine, inputs: &TickInputs, outcome: &TickOutcome) {
+        let player_index = |player_uuid: &String| {
+            engine
+                .players
+                .keys()
+                .position(|uuid| uuid == player_uuid)
+                .expect("player missing from the roster")
+        };
+        let mut active_players: Vec<usize> =
+            inputs.active_players.iter().map(player_index).collect();
+        active_players.sort();
+        self.ticks.push(ReplayTick(
+            active_players,
+            outcome
+                .played_moves
+                .iter()
+                .map(|played| {
+                    ReplayMove(
+                        player_index(&played.player_uuid),
+                        played.from,
+                        played.player_move.direction,
+                        played.player_move.half,
+                    )
+                })
+                .collect(),
+            outcome
+                .eliminations
+                .iter()
+                .filter(|elimination| elimination.by.is_none())
+                .map(|elimination| player_index(&elimination.player_uuid))
+                .collect(),
+        ));
+    }
+
+    // Re-simulates the game up to `tick` (0 being the board right after the kingdoms were placed)
+    pub fn simulate_until(&self, tick: usize) -> Option<(GameEngine, HashSet<String>)> {
+        if tick > self.ticks.len() {
+            return None;
+        }
+        let mut engine = GameEngine::new(
+            self.initial_board
+                .iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .map(|ReplayTile(tile_type, nb_troops)| Tile {
+                            tile_type: tile_type.clone(),
+                            nb_troops: *nb_troops,
+                            ..Tile::default()
+                        })
+                        .collect()
+                })
+                .collect(),
+        );
+        for (index, player) in self.players.iter().enumerate() {
+            engine.add_player(
+                replay_player_id(index),
+                player.name.clone(),
+                player.color.clone(),
+                player.kingdom_xy,
+                player.team,
+                player.is_bot,
+            );
+        }
+        let mut active_players: HashSet<String> =
+            (0..self.players.len()).map(replay_player_id).collect();
+        for ReplayTick(active, moves, surrenders) in self.ticks.iter().take(tick) {
+            for player in engine.players.values_mut() {
+                player.queued_moves.clear();
+            }
+            for ReplayMove(index, from, direction, half) in moves {
+                let player = engine
+                    .players
+                    .get_mut(&replay_player_id(*index))
+                    .expect("replay move from an unknown player");
+                player.xy = *from;
+                player.queued_moves.push_back(PlayerMove {
+                    direction: *direction,
+                    half: *half,
+                    from: None,
+                });
+            }
+            for index in surrenders {
+                engine.surrender(&replay_player_id(*index));
+            }
+            active_players = active
+                .iter()
+                .map(|index| replay_player_id(*index))
+                .collect();
+            engine.step(&TickInputs {
+                active_players: active_players.clone(),
+            });
+        }
+        Some((engine, active_players))
+    }
+}
+
+// Zero padded so that the engine resolves the players in the same order as during the game
+fn replay_player_id(index: usize) -> String {
+    format!("{:03}", index)
+}
+
+pub fn save_replay(state: &Arc<AppState>, replay: &Replay) -> Result<(), ServiceError> {
+    let data = serde_json::to_vec(replay).map_err(|_| ServiceError::Internal)?;
+    replay_dal::create_replay(state, replay.game_id.clone(), replay.lobby_id, data)?;
+    Ok(())
+}
+
+fn load_replay(state: &Arc<AppState>, game_id: String) -> Result<Replay, ServiceError> {
+    let data = replay_dal::get_replay_data(state, game_id)?;
+    serde_json::from_slice(&data).map_err(|_| ServiceError::Internal)
+}
+
+pub async fn get_replay(
+    State(state): State<Arc<AppState>>,
+    Path(game_id): Path<String>,
+) -> Result<(StatusCode, Json<ApiResponse<Replay>>), ServiceError> {
+    response_ok(Some(load_replay(&state, game_id)?))
+}
+
+pub async fn get_replay_tick(
+    State(state): State<Arc<AppState>>,
+    Path((game_id, tick)): Path<(String, usize)>,
+) -> Result<(StatusCode, Json<ApiResponse<ReplayTickResponse>>), ServiceError> {
+    let replay = load_replay(&state, game_id)?;
+    let (engine, active_players) = replay.simulate_until(tick).ok_or(ServiceError::NotFound)?;
+
+    response_ok(Some(ReplayTickResponse {
+        tick,
+        board_game: engine.full_view(),
+        score_board: engine.score_board(&active_players),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::service_layer::{
+        bot_service::{queue_bot_moves, BotDifficulty},
+        game_engine::GameStatus,
+        game_service::place_players,
+        map_generator::{generate_map, MapKind},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::HashMap;
+
+    // Bots play every seat while the game is recorded, one of them surrenders and another one drops
+    fn recorded_game(
+        nb_players: usize,
+        nb_teams: Option<usize>,
+        seed: u64,
+    ) -> (GameEngine, Replay) {
+        let map = generate_map(MapKind::Uniform, seed, nb_players);
+        let mut engine = GameEngine::new(map.board);
+        let mut replay = Replay::new("game".to_string(), 0, seed, &engine.board);
+        let players: Vec<(String, String, bool)> = (0..nb_players)
+            .map(|i| (format!("uuid-{}", i), format!("player-{}", i), i < 2)) // bots alone end the game
+            .collect();
+        place_players(&mut engine, &map.spawns, &players, nb_teams).unwrap();
+        replay.record_roster(&engine);
+        let bots: HashMap<String, BotDifficulty> = players
+            .iter()
+            .map(|(uuid, _, _)| (uuid.clone(), BotDifficulty::Medium))
+            .collect();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut inputs = TickInputs {
+            active_players: bots.keys().cloned().collect(),
+        };
+        for tick in 1..=300 {
+            if tick == 50 {
+                engine.surrender("uuid-0");
+            }
+            if tick == 80 {
+                inputs.active_players.remove("uuid-1");
+            }
+            queue_bot_moves(&mut engine, &bots, &mut rng);
+            let outcome = engine.step(&inputs);
+            replay.record_tick(&engine, &inputs, &outcome);
+            if outcome.status != GameStatus::Running {
+                break;
+            }
+        }
+        (engine, replay)
+    }
+
+    fn assert_same_game(played: &GameEngine, replay: &Replay) {
+        // the replay goes through its json form, the one saved in the database
+        let replay: Replay = serde_json::from_slice(&serde_json::to_vec(replay).unwrap()).unwrap();
+        let (simulated, _) = replay.simulate_until(replay.ticks.len()).unwrap();
+        assert_eq!(simulated.tick, played.tick);
+        assert!(played.tick > 80, "the game ended before a player dropped");
+        assert!(simulated.full_view() == played.full_view());
+        let placements = |engine: &GameEngine| {
+            engine
+                .ranking()
+                .into_iter()
+                .map(|player| (player.name, player.placement, player.total_troops))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(placements(&simulated), placements(played));
+    }
+
+    #[test]
+    fn re_simulation_gives_the_same_game() {
+        for seed in 0..3 {
+            let (played, replay) = recorded_game(4, None, seed);
+            assert_same_game(&played, &replay);
+        }
+    }
+
+    #[test]
+    fn re_simulation_gives_the_same_team_game() {
+        for seed in 0..3 {
+            let (played, replay) = recorded_game(4, Some(2), seed);
+            assert_same_game(&played, &replay);
+        }
+    }
+
+    #[test]
+    fn ticks_beyond_the_game_are_not_found() {
+        let (_, replay) = recorded_game(2, None, 0);
+        assert!(replay.simulate_until(replay.ticks.len() + 1).is_none());
+        let (start, _) = replay.simulate_until(0).unwrap();
+        assert_eq!(start.tick, 0);
+    }
+}
@@ -0,0 +1,56 @@
+use crate::custom_errors::service_errors::ApiResponseError;
+use crate::models::{
+    commons::WsProtocol,
+    messages_from_clients::{ClientCommand, ClientRequest},
+    messages_to_clients::{ServerMessage, WsMessageToClient},
+};
+use crate::requests::requests::{
+    GameResponse, IsValidPlayernameRequest, IsValidPlayernameResponse, LeaderboardRequest,
+    LeaderboardResponse, MapResponse, NewBotAccountRequest, NewBotAccountResponse,
+    PaginatedResponse, PaginationRequest, PlayerGameResponse, ReplayTickResponse,
+    RequestNewPlayerResponse, UpdateNameRequest, WsConnectionRequest,
+};
+use crate::utilities::responses::ApiResponse;
+use axum::Json;
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+
+use super::{map_service::CustomMap, replay_service::Replay};
+
+// Every type exchanged with the clients, the frontend generates its own types from the
+// schema of this struct instead of reading the rust code
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+struct Protocol {
+    // websocket, the text protocol sends the same messages as "/type <data as json>"
+    ws_connection_request: WsConnectionRequest,
+    ws_protocol: WsProtocol,
+    client_command: ClientCommand,
+    client_request: ClientRequest,
+    ws_message_to_client: WsMessageToClient,
+    server_message: ServerMessage<'static>,
+    // rest
+    api_response_error: ApiResponseError,
+    request_new_player_response: ApiResponse<RequestNewPlayerResponse>,
+    random_name_response: ApiResponse<String>,
+    is_valid_playername_request: IsValidPlayernameRequest,
+    is_valid_playername_response: ApiResponse<IsValidPlayernameResponse>,
+    new_bot_account_request: NewBotAccountRequest,
+    new_bot_account_response: ApiResponse<NewBotAccountResponse>,
+    update_name_request: UpdateNameRequest,
+    update_name_response: ApiResponse<String>,
+    pagination_request: PaginationRequest,
+    player_games_response: ApiResponse<PaginatedResponse<PlayerGameResponse>>,
+    game_response: ApiResponse<GameResponse>,
+    leaderboard_request: LeaderboardRequest,
+    leaderboard_response: ApiResponse<LeaderboardResponse>,
+    replay_response: ApiResponse<Replay>,
+    replay_tick_response: ApiResponse<ReplayTickResponse>,
+    upload_map_request: CustomMap,
+    upload_map_response: ApiResponse<MapResponse>,
+    maps_response: ApiResponse<PaginatedResponse<MapResponse>>,
+    map_response: ApiResponse<CustomMap>,
+}
+
+pub async fn get_schema() -> Json<RootSchema> {
+    Json(schema_for!(Protocol))
+}
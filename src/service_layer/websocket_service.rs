@@ -1,29 +1,40 @@
 use crate::configs;
-use crate::configs::app_state::{ChatMessage, LobbyStatus};
+use crate::configs::app_state::{ChatMessage, LobbyMap, LobbyStatus};
 use crate::constants::{
-    DELAY_FOR_GAMESTART_SEC, DISPLAY_N_LAST_MESSAGES, MAX_QUEUED_MOVES, NB_LOBBIES,
+    BOT_MAX_COMMANDS_PER_TICK, DELAY_FOR_GAMESTART_SEC, DISPLAY_N_LAST_MESSAGES, NB_LOBBIES,
+    RECONNECT_GRACE_PERIOD_SEC, TICK_GAME_INTERVAL_MS,
 };
-use crate::data_access_layer::player_dal::{self, Player};
-use crate::models::messages_from_clients::ClientCommand;
+use crate::custom_errors::command_errors::CommandError;
+use crate::data_access_layer::player_dal::Player;
+use crate::models::{commons::WsProtocol, messages_from_clients::ClientCommand};
 use crate::models::{
+    messages_to_clients::CommandRejection, messages_to_clients::GameUpdate,
     messages_to_clients::LobbiesGeneralUpdate, messages_to_clients::LobbyGeneralUpdate,
     messages_to_clients::WsMessageToClient,
 };
-use crate::service_layer::player_service::{self, Color};
+use crate::service_layer::{
+    bot_service::add_bot,
+    game_engine::GameEngine,
+    game_service::active_players,
+    map_service::{get_custom_map, load_map},
+    player_service,
+};
 use axum::extract::ws::{Message, WebSocket};
 use chrono::Utc;
 use futures_util::{
     sink::SinkExt,
     stream::{SplitStream, StreamExt},
 };
-use std::collections::VecDeque;
 use std::sync::Arc;
 use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::time::{Duration, Instant};
 
 pub async fn handle_websocket(
     player: Player,
     socket: WebSocket,
     state: Arc<configs::app_state::AppState>,
+    protocol: WsProtocol,
+    is_bot: bool,
 ) {
     let (mut sender, mut receiver) = socket.split();
 
@@ -41,11 +52,10 @@ pub async fn handle_websocket(
                 name: player.name.clone(),
                 personal_tx: perso_tx.clone(),
                 playing_in_lobby: None,
-                queued_moves: VecDeque::new(),
-                xy: (0, 0),
-                color: Color::Red,
+                is_bot,
             },
         );
+    resume_lobby(&state, &player.uuid);
 
     println!("CURRENT PLAYERS {:?}", state.players);
 
@@ -65,12 +75,14 @@ pub async fn handle_websocket(
             cloned_player.name,
             cloned_player.uuid,
             cloned_state,
+            protocol,
+            is_bot,
         )
         .await
     });
 
     global_lobbies_update(state.clone());
-    global_chat_sync(perso_tx, state.clone());
+    global_chat_sync(perso_tx.clone(), state.clone());
 
     let cloned_state = state.clone();
     let mut message_controler = tokio::spawn(async move {
@@ -80,7 +92,7 @@ pub async fn handle_websocket(
                     // println!("global msg {:?}", elem);
                     match elem {
                         Ok(msg) => {
-                            let _ = sender.send(msg.to_string_message()).await;
+                            let _ = sender.send(msg.to_message(protocol)).await;
                         },
                         Err(e) => {
                            println!("eeee 1 {}", e);
@@ -91,7 +103,7 @@ pub async fn handle_websocket(
                     // println!("lobby msg {:?}", elem);
                     match elem {
                         Ok(msg) => {
-                            let _ = sender.send(msg.to_string_message()).await;
+                            let _ = sender.send(msg.to_message(protocol)).await;
                         },
                         Err(e) => {
                            println!("eeee 2 {}", e);
@@ -106,10 +118,10 @@ pub async fn handle_websocket(
                                 WsMessageToClient::JoinLobby(lobby_id) => {
                                     if lobby_id < NB_LOBBIES { // lobby_id 0 indexed
                                         lobby_subscription = cloned_state.lobbies[lobby_id].read().unwrap().lobby_broadcast.subscribe();
-                                        let _ = sender.send(msg.to_string_message()).await;
+                                        let _ = sender.send(msg.to_message(protocol)).await;
                                     }
                                 },
-                                _ => {let _ = sender.send(msg.to_string_message()).await;}
+                                _ => {let _ = sender.send(msg.to_message(protocol)).await;}
                             };
                         },
                         None => {
@@ -139,33 +151,42 @@ pub async fn handle_websocket(
     };
 
     // Handle player disconnecting :
-    // 1. Remove from the lobby (except when already in game)
-    if let Some(lobby_id) = state
+    // 0. Nothing to do if the player already reconnected, the new connection took over
+    let playing_in_lobby = match state
         .players
         .read()
         .expect("failed to read players")
         .get(&player.uuid)
-        .expect("player uuid not found")
-        .playing_in_lobby
     {
-        let lobby_status = state.lobbies[lobby_id]
-            .read()
-            .expect("failed to read lobby")
-            .status;
-        match lobby_status {
-            LobbyStatus::InGame => (), // don't remove from lobby, the player will become inactive instead
-            LobbyStatus::StartingSoon => (), // don't remove from lobby, the player will become inactive instead
+        Some(connected) if connected.personal_tx.same_channel(&perso_tx) => {
+            connected.playing_in_lobby
+        }
+        _ => return,
+    };
+
+    // 1. Remove from the lobby (except when already in game)
+    if let Some(lobby_id) = playing_in_lobby {
+        let mut lobby = state.lobbies[lobby_id]
+            .write()
+            .expect("failed to write lobby");
+        match lobby.status {
+            // don't remove from lobby, the player can come back before becoming inactive
+            LobbyStatus::InGame | LobbyStatus::StartingSoon => {
+                lobby.disconnected.insert(
+                    player.uuid.clone(),
+                    Utc::now().timestamp() + RECONNECT_GRACE_PERIOD_SEC,
+                );
+            }
             LobbyStatus::AwaitingPlayers => {
-                state.lobbies[lobby_id]
-                    .write()
-                    .expect("failed to write lobby")
-                    .players
-                    .remove(&player.uuid);
+                lobby.remove_player(&player.uuid);
             }
         }
     }
 
-    // 2. Remove from connected players list
+    // 2. Stop spectating
+    stop_spectating(&state, &player.uuid);
+
+    // 3. Remove from connected players list
     state
         .players
         .write()
@@ -179,163 +200,378 @@ async fn receive(
     player_name: String, // todo : what if player changes name and is still connected ?
     player_uuid: String,
     state: Arc<configs::app_state::AppState>,
+    protocol: WsProtocol,
+    is_bot: bool,
 ) {
+    let mut rate_window_start = Instant::now();
+    let mut nb_commands_in_window = 0;
     // todo : add disconnected for afk, spawn task waiter, reset to 0 after each message, if time > 100 : return err afk
-    'rec_v_loop: while let Some(Ok(message)) = receiver.next().await {
-        match message {
-            Message::Text(msg) => {
-                let command = msg.parse::<ClientCommand>();
-                println!("new command {:?}", command);
-                if let Ok(c) = command {
-                    match c {
-                        ClientCommand::Move(new_move) => {
-                            let mut players =
-                                state.players.write().expect("failed to lock players");
-                            let player = players.get_mut(&player_uuid).expect("msg");
-                            if player.queued_moves.len() < MAX_QUEUED_MOVES {
-                                player.queued_moves.push_back(new_move)
-                            }
-                            player
-                                .personal_tx
-                                .send(WsMessageToClient::QueuedMoves(
-                                    player_service::PlayerMoves {
-                                        queued_moves: player.queued_moves.clone(),
-                                        xy: player.xy,
-                                    },
-                                ))
-                                .expect("failed to notify current lobby chat");
-                        }
-                        ClientCommand::JoinLobby(join_lobby_id) => {
-                            println!("JOIN LOBBY {:?}", join_lobby_id);
-                            if join_lobby_id < NB_LOBBIES {
-                                let mut players =
-                                    state.players.write().expect("failed to lock players");
-                                let mut lobby_to_join = state.lobbies[join_lobby_id]
-                                    .write()
-                                    .expect("failed ot lock lobby");
-                                if lobby_to_join.players.len() >= lobby_to_join.player_capacity {
-                                    continue 'rec_v_loop;
-                                }
-                                if lobby_to_join.status != LobbyStatus::AwaitingPlayers {
-                                    continue 'rec_v_loop;
-                                }
-                                let player = players
-                                    .get_mut(&player_uuid)
-                                    .expect("failed to get playername");
-                                if let Some(in_lobby) = player.playing_in_lobby {
-                                    if in_lobby == join_lobby_id {
-                                        // don't join a lobby you're already in
-                                        continue 'rec_v_loop;
-                                    }
-                                    // remove from current lobby before joining the new one
-                                    state.lobbies[in_lobby]
-                                        .write()
-                                        .unwrap()
-                                        .players
-                                        .remove(&player_uuid.clone());
-                                }
-                                lobby_to_join
-                                    .players
-                                    .insert(player_uuid.clone(), player_name.clone());
-                                player.playing_in_lobby = Some(join_lobby_id);
-                                if lobby_to_join.players.len() == lobby_to_join.player_capacity {
-                                    // Start the game soon..
-                                    println!("lobby {} is starting", lobby_to_join.lobby_id);
-                                    lobby_to_join.status = LobbyStatus::StartingSoon;
-                                    lobby_to_join.next_starting_time =
-                                        Utc::now().timestamp() + DELAY_FOR_GAMESTART_SEC;
-                                }
-                                drop(lobby_to_join);
-                                player
-                                    .personal_tx
-                                    .send(WsMessageToClient::JoinLobby(join_lobby_id))
-                                    .expect("failed to notify joined lobby");
-                                player
-                                    .personal_tx
-                                    .send(WsMessageToClient::LobbyChatSync(
-                                        state.lobbies[join_lobby_id]
-                                            .read()
-                                            .expect("failed to lock lobby chat")
-                                            .messages
-                                            .clone()
-                                            .into_iter()
-                                            .skip(
-                                                state.lobbies[join_lobby_id]
-                                                    .read()
-                                                    .expect("failed to lock lobby chat")
-                                                    .messages
-                                                    .len()
-                                                    .saturating_sub(DISPLAY_N_LAST_MESSAGES),
-                                            )
-                                            .take(DISPLAY_N_LAST_MESSAGES)
-                                            .collect::<Vec<ChatMessage>>(),
-                                    ))
-                                    .expect("lobby chat sync failed");
+    while let Some(Ok(message)) = receiver.next().await {
+        let (request_id, mut command) = match message {
+            Message::Text(text) if protocol == WsProtocol::Json => ClientCommand::from_json(&text),
+            Message::Text(text) => (
+                None,
+                text.parse::<ClientCommand>()
+                    .map_err(|_| CommandError::InvalidCommand),
+            ),
+            Message::Binary(frame) => (None, ClientCommand::from_binary(&frame)),
+            _ => {
+                println!("received unexpected message {:?}", message);
+                break;
+            }
+        };
+        // bot accounts are limited to a few commands per tick
+        if is_bot {
+            if rate_window_start.elapsed() >= Duration::from_millis(TICK_GAME_INTERVAL_MS) {
+                rate_window_start = Instant::now();
+                nb_commands_in_window = 0;
+            }
+            nb_commands_in_window += 1;
+            if nb_commands_in_window > BOT_MAX_COMMANDS_PER_TICK {
+                command = Err(CommandError::RateLimited);
+            }
+        }
+        println!("new command {:?}", command);
+        if let Err(code) =
+            command.and_then(|command| handle_command(command, &player_name, &player_uuid, &state))
+        {
+            state
+                .players
+                .read()
+                .expect("couldnt lock players")
+                .get(&player_uuid)
+                .expect("failed to get player")
+                .personal_tx
+                .send(WsMessageToClient::Error(CommandRejection {
+                    code,
+                    message: code.error_message(),
+                    request_id,
+                }))
+                .expect("failed to notify rejected command");
+        }
+    }
+}
 
-                                drop(players); // unlock players because we are trying to lock it in global_lobby_update
-                                global_lobbies_update(state.clone());
-                            }
-                        }
-                        ClientCommand::Ping => {
-                            state
-                                .players
-                                .read()
-                                .expect("couldnt lock players")
-                                .get(&player_uuid)
-                                .expect("failed to get playername")
-                                .personal_tx
-                                .send(WsMessageToClient::Pong)
-                                .expect("failed to pong player");
-                        }
-                        ClientCommand::SendGlobalMessage(message) => {
-                            state
-                                .global_chat_messages
-                                .write()
-                                .expect("failed to lock global chat")
-                                .push(ChatMessage {
-                                    poster: player_name.clone(),
-                                    message: message.clone(),
-                                });
-                            global_chat_new_message(state.clone(), message, player_name.clone());
-                        }
-                        ClientCommand::SendLobbyMessage(message) => {
-                            if let Some(lobby_player) = state
-                                .players
+fn handle_command(
+    command: ClientCommand,
+    player_name: &str,
+    player_uuid: &str,
+    state: &Arc<configs::app_state::AppState>,
+) -> Result<(), CommandError> {
+    match command {
+        ClientCommand::Move(new_move) => edit_moves(state, player_uuid, |engine| {
+            if new_move.from.is_some_and(|from| !engine.is_on_board(from)) {
+                return Err(CommandError::OutOfBoard);
+            }
+            engine
+                .queue_move(player_uuid, new_move)
+                .then_some(())
+                .ok_or(CommandError::QueueFull)
+        })?,
+        ClientCommand::Select(x, y) => edit_moves(state, player_uuid, |engine| {
+            engine
+                .select(player_uuid, (x, y))
+                .then_some(())
+                .ok_or(CommandError::TileNotOwned)
+        })?,
+        ClientCommand::Goto(x, y) => edit_moves(state, player_uuid, |engine| {
+            if !engine.is_on_board((x, y)) {
+                return Err(CommandError::OutOfBoard);
+            }
+            let path = engine
+                .path_to(player_uuid, (x, y))
+                .ok_or(CommandError::NoPath)?;
+            engine
+                .queue_path(player_uuid, &path)
+                .then_some(())
+                .ok_or(CommandError::QueueFull)
+        })?,
+        ClientCommand::ClearMoves => edit_moves(state, player_uuid, |engine| {
+            engine.clear_moves(player_uuid);
+            Ok(())
+        })?,
+        ClientCommand::UndoMove => edit_moves(state, player_uuid, |engine| {
+            engine
+                .undo_move(player_uuid)
+                .then_some(())
+                .ok_or(CommandError::NoSuchMove)
+        })?,
+        ClientCommand::CancelMovesFrom(index) => edit_moves(state, player_uuid, |engine| {
+            engine
+                .cancel_moves_from(player_uuid, index)
+                .then_some(())
+                .ok_or(CommandError::NoSuchMove)
+        })?,
+        ClientCommand::Surrender => {
+            let lobby_id = state
+                .players
+                .read()
+                .expect("failed to lock players")
+                .get(player_uuid)
+                .expect("failed to get player")
+                .playing_in_lobby
+                .ok_or(CommandError::NotInGame)?;
+            let mut lobby = state.lobbies[lobby_id]
+                .write()
+                .expect("failed to lock lobby");
+            if lobby.status != LobbyStatus::InGame || !lobby.game_engine.surrender(player_uuid) {
+                return Err(CommandError::NotInGame);
+            }
+        }
+        ClientCommand::Resync => {
+            let lobby_id = state
+                .players
+                .read()
+                .expect("failed to lock players")
+                .get(player_uuid)
+                .expect("failed to get player")
+                .playing_in_lobby
+                .ok_or(CommandError::NotInGame)?;
+            // the next tick sends the full board again
+            state.lobbies[lobby_id]
+                .write()
+                .expect("failed to lock lobby")
+                .sent_boards
+                .remove(player_uuid);
+        }
+        ClientCommand::JoinLobby(join_lobby_id) => {
+            println!("JOIN LOBBY {:?}", join_lobby_id);
+            if join_lobby_id >= NB_LOBBIES {
+                return Err(CommandError::UnknownLobby);
+            }
+            let mut players = state.players.write().expect("failed to lock players");
+            let mut lobby_to_join = state.lobbies[join_lobby_id]
+                .write()
+                .expect("failed ot lock lobby");
+            if lobby_to_join.players.len() >= lobby_to_join.player_capacity {
+                return Err(CommandError::LobbyFull);
+            }
+            if lobby_to_join.status != LobbyStatus::AwaitingPlayers {
+                return Err(CommandError::LobbyAlreadyStarted);
+            }
+            let player = players
+                .get_mut(player_uuid)
+                .expect("failed to get playername");
+            match (lobby_to_join.bots_only, player.is_bot) {
+                (true, false) => return Err(CommandError::ReservedToBots),
+                (false, true) => return Err(CommandError::ReservedToPlayers),
+                _ => (),
+            }
+            if let Some(in_lobby) = player.playing_in_lobby {
+                if in_lobby == join_lobby_id {
+                    // don't join a lobby you're already in
+                    return Err(CommandError::AlreadyInLobby);
+                }
+                // remove from current lobby before joining the new one
+                state.lobbies[in_lobby]
+                    .write()
+                    .unwrap()
+                    .remove_player(player_uuid);
+            }
+            if lobby_to_join.players.is_empty() {
+                lobby_to_join.awaiting_since = Utc::now().timestamp();
+            }
+            lobby_to_join
+                .players
+                .insert(player_uuid.to_string(), player_name.to_string());
+            player.playing_in_lobby = Some(join_lobby_id);
+            if lobby_to_join.players.len() == lobby_to_join.player_capacity {
+                // Start the game soon..
+                println!("lobby {} is starting", lobby_to_join.lobby_id);
+                lobby_to_join.status = LobbyStatus::StartingSoon;
+                lobby_to_join.next_starting_time = Utc::now().timestamp() + DELAY_FOR_GAMESTART_SEC;
+            }
+            drop(lobby_to_join);
+            if let Some(spectated_lobby_id) = stop_spectating(state, player_uuid) {
+                let _ = player
+                    .personal_tx
+                    .send(WsMessageToClient::SpectatingEnded(spectated_lobby_id));
+            }
+            player
+                .personal_tx
+                .send(WsMessageToClient::JoinLobby(join_lobby_id))
+                .expect("failed to notify joined lobby");
+            player
+                .personal_tx
+                .send(WsMessageToClient::LobbyChatSync(
+                    state.lobbies[join_lobby_id]
+                        .read()
+                        .expect("failed to lock lobby chat")
+                        .messages
+                        .clone()
+                        .into_iter()
+                        .skip(
+                            state.lobbies[join_lobby_id]
                                 .read()
-                                .expect("couldnt lock players")
-                                .get(&player_uuid)
-                                .expect("couldnt find playername")
-                                .playing_in_lobby
-                            {
-                                // can only send messages in the lobby youre in
-                                state.lobbies[lobby_player]
-                                    .write()
-                                    .expect("failed ot lock lobby")
-                                    .messages
-                                    .push(ChatMessage {
-                                        poster: player_name.clone(),
-                                        message: message.clone(),
-                                    });
-                                state.lobbies[lobby_player]
-                                    .read()
-                                    .unwrap()
-                                    .lobby_broadcast
-                                    .send(WsMessageToClient::LobbyChatNewMessage(ChatMessage {
-                                        poster: player_name.clone(),
-                                        message,
-                                    }))
-                                    .expect("failed to notify of new lobby message");
-                            }
-                        }
-                    }
+                                .expect("failed to lock lobby chat")
+                                .messages
+                                .len()
+                                .saturating_sub(DISPLAY_N_LAST_MESSAGES),
+                        )
+                        .take(DISPLAY_N_LAST_MESSAGES)
+                        .collect::<Vec<ChatMessage>>(),
+                ))
+                .expect("lobby chat sync failed");
+
+            drop(players); // unlock players because we are trying to lock it in global_lobby_update
+            global_lobbies_update(state.clone());
+        }
+        ClientCommand::InviteBot(difficulty) => {
+            let lobby_id = state
+                .players
+                .read()
+                .expect("failed to lock players")
+                .get(player_uuid)
+                .expect("failed to get player")
+                .playing_in_lobby
+                .ok_or(CommandError::NotInLobby)?;
+            let mut lobby = state.lobbies[lobby_id]
+                .write()
+                .expect("failed to lock lobby");
+            if lobby.status != LobbyStatus::AwaitingPlayers {
+                return Err(CommandError::LobbyAlreadyStarted);
+            }
+            if lobby.players.len() >= lobby.player_capacity {
+                return Err(CommandError::LobbyFull);
+            }
+            add_bot(&mut lobby, difficulty);
+            drop(lobby);
+            global_lobbies_update(state.clone());
+        }
+        ClientCommand::UseMap(map_id) => {
+            let lobby_id = state
+                .players
+                .read()
+                .expect("failed to lock players")
+                .get(player_uuid)
+                .expect("failed to get player")
+                .playing_in_lobby
+                .ok_or(CommandError::NotInLobby)?;
+            let custom_map = match map_id {
+                Some(map_id) => {
+                    let custom_map = get_custom_map(state, map_id.clone())
+                        .map_err(|_| CommandError::UnknownMap)?;
+                    Some(LobbyMap {
+                        map_id,
+                        name: custom_map.name.clone(),
+                        map: load_map(&custom_map).map_err(|_| CommandError::UnknownMap)?,
+                    })
                 }
+                None => None,
+            };
+            let mut lobby = state.lobbies[lobby_id]
+                .write()
+                .expect("failed to lock lobby");
+            if lobby.status != LobbyStatus::AwaitingPlayers {
+                return Err(CommandError::LobbyAlreadyStarted);
             }
-            _ => {
-                println!("received unexpected message {:?}", message);
-                break 'rec_v_loop;
+            if lobby.is_ranked {
+                return Err(CommandError::RankedLobby);
+            }
+            if custom_map
+                .as_ref()
+                .is_some_and(|custom_map| custom_map.map.spawns.len() < lobby.player_capacity)
+            {
+                return Err(CommandError::MapTooSmall);
             }
+            lobby.custom_map = custom_map;
+            lobby.generate_new_board();
+            drop(lobby);
+            global_lobbies_update(state.clone());
+        }
+        ClientCommand::Spectate(spectate_lobby_id) => {
+            if spectate_lobby_id >= NB_LOBBIES {
+                return Err(CommandError::UnknownLobby);
+            }
+            stop_spectating(state, player_uuid);
+            let mut lobby_to_spectate = state.lobbies[spectate_lobby_id]
+                .write()
+                .expect("failed to lock lobby");
+            // only running games can be watched, and not by their own players
+            if lobby_to_spectate.status != LobbyStatus::InGame
+                || lobby_to_spectate.players.contains_key(player_uuid)
+            {
+                return Err(CommandError::CannotSpectate);
+            }
+            lobby_to_spectate.spectators.insert(player_uuid.to_string());
+            drop(lobby_to_spectate);
+            state
+                .players
+                .read()
+                .expect("couldnt lock players")
+                .get(player_uuid)
+                .expect("failed to get player")
+                .personal_tx
+                .send(WsMessageToClient::Spectating(spectate_lobby_id))
+                .expect("failed to notify spectated lobby");
+            global_lobbies_update(state.clone());
+        }
+        ClientCommand::StopSpectating => {
+            let spectated_lobby_id =
+                stop_spectating(state, player_uuid).ok_or(CommandError::NotSpectating)?;
+            state
+                .players
+                .read()
+                .expect("couldnt lock players")
+                .get(player_uuid)
+                .expect("failed to get player")
+                .personal_tx
+                .send(WsMessageToClient::SpectatingEnded(spectated_lobby_id))
+                .expect("failed to notify end of spectating");
+            global_lobbies_update(state.clone());
+        }
+        ClientCommand::Ping => {
+            state
+                .players
+                .read()
+                .expect("couldnt lock players")
+                .get(player_uuid)
+                .expect("failed to get playername")
+                .personal_tx
+                .send(WsMessageToClient::Pong)
+                .expect("failed to pong player");
+        }
+        ClientCommand::SendGlobalMessage(message) => {
+            state
+                .global_chat_messages
+                .write()
+                .expect("failed to lock global chat")
+                .push(ChatMessage {
+                    poster: player_name.to_string(),
+                    message: message.clone(),
+                });
+            global_chat_new_message(state.clone(), message, player_name.to_string());
+        }
+        ClientCommand::SendLobbyMessage(message) => {
+            // can only send messages in the lobby youre in
+            let lobby_player = state
+                .players
+                .read()
+                .expect("couldnt lock players")
+                .get(player_uuid)
+                .expect("couldnt find playername")
+                .playing_in_lobby
+                .ok_or(CommandError::NotInLobby)?;
+            state.lobbies[lobby_player]
+                .write()
+                .expect("failed ot lock lobby")
+                .messages
+                .push(ChatMessage {
+                    poster: player_name.to_string(),
+                    message: message.clone(),
+                });
+            state.lobbies[lobby_player]
+                .read()
+                .unwrap()
+                .lobby_broadcast
+                .send(WsMessageToClient::LobbyChatNewMessage(ChatMessage {
+                    poster: player_name.to_string(),
+                    message,
+                }))
+                .expect("failed to notify of new lobby message");
         }
     }
+    Ok(())
 }
 
 pub fn global_lobbies_update(state: Arc<configs::app_state::AppState>) {
@@ -354,7 +590,17 @@ pub fn global_lobbies_update(state: Arc<configs::app_state::AppState>) {
         let lobby = lob.read().expect("failed to lock lobby");
         update.lobbies.push(LobbyGeneralUpdate {
             player_capacity: lobby.player_capacity,
+            is_ranked: lobby.is_ranked,
+            team_size: lobby.team_size,
+            bots_only: lobby.bots_only,
+            map_kind: lobby.map_kind,
+            custom_map: lobby
+                .custom_map
+                .as_ref()
+                .map(|custom_map| custom_map.name.clone()),
             player_names: lobby.players.values().cloned().collect(),
+            nb_bots: lobby.bots.len(),
+            nb_spectators: lobby.spectators.len(),
             status: lobby.status,
             next_starting_time: lobby.next_starting_time,
         });
@@ -365,6 +611,101 @@ pub fn global_lobbies_update(state: Arc<configs::app_state::AppState>) {
         .expect("global lobbies update failed");
 }
 
+// Edits the moves of a player still in a running game, the new queue is echoed back
+fn edit_moves(
+    state: &Arc<configs::app_state::AppState>,
+    player_uuid: &str,
+    edit: impl FnOnce(&mut GameEngine) -> Result<(), CommandError>,
+) -> Result<(), CommandError> {
+    let players = state.players.read().expect("failed to lock players");
+    let player = players.get(player_uuid).expect("failed to get player");
+    let lobby_id = player.playing_in_lobby.ok_or(CommandError::NotInGame)?;
+    let mut lobby = state.lobbies[lobby_id]
+        .write()
+        .expect("failed to lock lobby");
+    if lobby.status != LobbyStatus::InGame
+        || !lobby.game_engine.players.contains_key(player_uuid)
+        || lobby.game_engine.is_eliminated(player_uuid)
+    {
+        return Err(CommandError::NotInGame);
+    }
+    edit(&mut lobby.game_engine)?;
+    if let Some(moves) = lobby.game_engine.player_moves(player_uuid) {
+        player
+            .personal_tx
+            .send(WsMessageToClient::QueuedMoves(moves))
+            .expect("failed to notify queued moves");
+    }
+    Ok(())
+}
+
+// A player reconnecting within the grace period takes back its place in its lobby
+fn resume_lobby(state: &Arc<configs::app_state::AppState>, player_uuid: &str) {
+    let now = Utc::now().timestamp();
+    let mut players = state.players.write().expect("failed to lock players");
+    let lobby_id = match (0..NB_LOBBIES).find(|lobby_id| {
+        let mut lobby = state.lobbies[*lobby_id]
+            .write()
+            .expect("failed to lock lobby");
+        let is_reconnecting = lobby
+            .disconnected
+            .remove(player_uuid)
+            .is_some_and(|deadline| deadline >= now);
+        is_reconnecting && lobby.players.contains_key(player_uuid)
+    }) {
+        Some(lobby_id) => lobby_id,
+        None => return,
+    };
+    players
+        .get_mut(player_uuid)
+        .expect("player uuid not found")
+        .playing_in_lobby = Some(lobby_id);
+
+    let mut lobby = state.lobbies[lobby_id]
+        .write()
+        .expect("failed to lock lobby");
+    let player = &players[player_uuid];
+    let _ = player
+        .personal_tx
+        .send(WsMessageToClient::JoinLobby(lobby_id));
+    if lobby.status == LobbyStatus::InGame {
+        let _ = player
+            .personal_tx
+            .send(WsMessageToClient::GameStarted(lobby_id));
+        if let Some(moves) = lobby.game_engine.player_moves(player_uuid) {
+            // no need to wait for the next tick to display the game again
+            let board_game = lobby.game_engine.fog_of_war_view(player_uuid);
+            let _ = player
+                .personal_tx
+                .send(WsMessageToClient::GameUpdate(GameUpdate {
+                    board_game: board_game.clone(),
+                    score_board: lobby
+                        .game_engine
+                        .score_board(&active_players(&lobby, &players)),
+                    moves,
+                    rejected_move: None,
+                    tick: lobby.game_engine.tick,
+                }));
+            let tick = lobby.game_engine.tick;
+            lobby
+                .sent_boards
+                .insert(player_uuid.to_string(), (tick, board_game));
+        }
+    }
+}
+
+// Returns the lobby the player was watching, if any
+fn stop_spectating(state: &Arc<configs::app_state::AppState>, player_uuid: &str) -> Option<usize> {
+    state.lobbies.iter().find_map(|lobby| {
+        let mut lobby = lobby.write().expect("failed to lock lobby");
+        if lobby.spectators.remove(player_uuid) {
+            Some(lobby.lobby_id)
+        } else {
+            None
+        }
+    })
+}
+
 fn global_chat_sync(
     perso_tx: tokio::sync::mpsc::UnboundedSender<WsMessageToClient>,
     state: Arc<configs::app_state::AppState>,
@@ -1,10 +1,11 @@
 use axum::{http::StatusCode, Json};
+use schemars::JsonSchema;
 use serde::Serialize;
 
 use crate::custom_errors::service_errors::ServiceError;
 // use crate::service_layer::auth_service::AuthError;
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ApiResponse<T: Serialize> {
     pub message: Option<String>,
     pub code: u16,
@@ -25,6 +26,7 @@ pub fn response_ok<T: Serialize>(
     ))
 }
 
+#[allow(dead_code)]
 pub fn response_ok_with_message<T: Serialize>(
     data: Option<T>,
     message: String,